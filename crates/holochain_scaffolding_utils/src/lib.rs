@@ -1,9 +1,22 @@
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
 
-use dialoguer::{theme::ColorfulTheme, Select};
-use file_tree_utils::{find_files_by_name, FileTree, FileTreeError};
-use holochain_types::{app::AppManifest, web_app::WebAppManifest};
+use file_tree_utils::{
+    file_content, find_files_by_extension, find_files_by_name, insert_file, map_file, FileTree,
+    FileTreeError,
+};
+use holochain_types::{app::AppManifest, prelude::DnaManifest, web_app::WebAppManifest};
 use mr_bundle::Manifest;
+use path_clean::PathClean;
+use prompt_utils::{Chooser, PromptError};
+use regex::Regex;
+use rnix::{ast, SyntaxNode};
+use rowan::ast::AstNode;
+
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -11,20 +24,48 @@ pub enum Error {
     FileTreeError(#[from] FileTreeError),
 
     #[error(transparent)]
-    DialoguerError(#[from] dialoguer::Error),
+    PromptError(#[from] PromptError),
 
     #[error(transparent)]
     SerdeYamlError(#[from] serde_yaml::Error),
 
+    #[error(transparent)]
+    RegexError(#[from] regex::Error),
+
     #[error("No web-happ.yaml files were found in this repository")]
     WebAppManifestNotFound,
 
     #[error("No happ.yaml files were found in this repository")]
     AppManifestNotFound,
+
+    #[error("Malformed DNA manifest at {0}: {1}")]
+    MalformedDnaManifest(PathBuf, String),
+
+    #[error("No nixified DNAs were found in this repository")]
+    NoDnasFound,
+
+    #[error("The dna {0} was not found in this repository")]
+    DnaNotFound(String),
+
+    #[error("No \"zomes = {{\" code property was found in the nix file {0:?}")]
+    ZomesPropertyNotFound(PathBuf),
+
+    #[error("No integrity or coordinator zomes were specified to be added to the DNA")]
+    NoZomesSpecified,
+
+    #[error("The zomes in the nix file {0:?} are inherited from another expression, so they can't be edited")]
+    UnsupportedZomesProperty(PathBuf),
+
+    #[error("Malformed nix file {0:?}: {1}")]
+    MalformedNixFile(PathBuf, String),
+
+    #[error("The zome {0} already exists in the DNA {1}")]
+    ZomeAlreadyExists(String, String),
 }
 
 pub fn get_or_choose_web_app_manifest(
     file_tree: &FileTree,
+    chooser: &dyn Chooser,
 ) -> Result<(PathBuf, WebAppManifest), Error> {
     let web_app_manifests = find_web_app_manifests(&file_tree)?;
 
@@ -34,7 +75,7 @@ pub fn get_or_choose_web_app_manifest(
             .into_iter()
             .last()
             .ok_or(Error::WebAppManifestNotFound),
-        _ => choose_web_app(web_app_manifests),
+        _ => choose_web_app(web_app_manifests, chooser),
     }?;
 
     Ok(app_manifest)
@@ -42,6 +83,7 @@ pub fn get_or_choose_web_app_manifest(
 
 pub fn choose_web_app(
     app_manifests: BTreeMap<PathBuf, WebAppManifest>,
+    chooser: &dyn Chooser,
 ) -> Result<(PathBuf, WebAppManifest), Error> {
     let manifest_vec: Vec<(PathBuf, WebAppManifest)> = app_manifests.into_iter().collect();
     let app_names: Vec<String> = manifest_vec
@@ -49,11 +91,11 @@ pub fn choose_web_app(
         .map(|(_, m)| m.app_name().to_string())
         .collect();
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Multiple web-happs were found in this repository, choose one:")
-        .default(0)
-        .items(&app_names[..])
-        .interact()?;
+    let selection = chooser.choose(
+        &String::from("Multiple web-happs were found in this repository, choose one:"),
+        &app_names,
+        0,
+    )?;
 
     Ok(manifest_vec[selection].clone())
 }
@@ -77,7 +119,10 @@ pub fn find_web_app_manifests(
     Ok(manifests)
 }
 
-pub fn get_or_choose_app_manifest(file_tree: &FileTree) -> Result<(PathBuf, AppManifest), Error> {
+pub fn get_or_choose_app_manifest(
+    file_tree: &FileTree,
+    chooser: &dyn Chooser,
+) -> Result<(PathBuf, AppManifest), Error> {
     let app_manifests = find_app_manifests(&file_tree)?;
 
     let app_manifest = match app_manifests.len() {
@@ -86,7 +131,7 @@ pub fn get_or_choose_app_manifest(file_tree: &FileTree) -> Result<(PathBuf, AppM
             .into_iter()
             .last()
             .ok_or(Error::AppManifestNotFound),
-        _ => choose_app(app_manifests),
+        _ => choose_app(app_manifests, chooser),
     }?;
 
     Ok(app_manifest)
@@ -94,6 +139,7 @@ pub fn get_or_choose_app_manifest(file_tree: &FileTree) -> Result<(PathBuf, AppM
 
 pub fn choose_app(
     app_manifests: BTreeMap<PathBuf, AppManifest>,
+    chooser: &dyn Chooser,
 ) -> Result<(PathBuf, AppManifest), Error> {
     let manifest_vec: Vec<(PathBuf, AppManifest)> = app_manifests.into_iter().collect();
     let app_names: Vec<String> = manifest_vec
@@ -101,11 +147,11 @@ pub fn choose_app(
         .map(|(_, m)| m.app_name().to_string())
         .collect();
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Multiple happs were found in this repository, choose one:")
-        .default(0)
-        .items(&app_names[..])
-        .interact()?;
+    let selection = chooser.choose(
+        &String::from("Multiple happs were found in this repository, choose one:"),
+        &app_names,
+        0,
+    )?;
 
     Ok(manifest_vec[selection].clone())
 }
@@ -128,3 +174,943 @@ pub fn find_app_manifests(
 
     Ok(manifests)
 }
+
+/// A DNA whose manifest is referenced from a nix file through `dnaManifest = ./path/to/dna.yaml;`
+#[derive(Debug, Clone)]
+pub struct NixifiedDna {
+    /// Name of the DNA, as declared in its manifest
+    pub name: String,
+    /// Name of the nix package that builds the DNA
+    pub package_name: String,
+    pub dna_nix: (PathBuf, String),
+    pub dna_manifest: (PathBuf, String),
+    pub dna_manifest_reference_index: usize,
+}
+
+/// Returns all the DNAs in the given file tree that are built with nix
+pub fn find_nixified_dnas(file_tree: &FileTree) -> Result<Vec<NixifiedDna>, Error> {
+    // Find all dna.yaml files
+    // Go through all nix files, finding `dnaManifest = `
+    // For each DNA, find the ones that are referred to from the nix files, those are the eligible DNAs
+    let dna_yaml_files = find_files_by_name(file_tree, PathBuf::from("dna.yaml").as_path());
+
+    let mut nixified_dnas: Vec<NixifiedDna> = Vec::new();
+
+    let nix_files: Vec<(PathBuf, String, Vec<DnaManifestReference>)> =
+        find_files_by_extension(file_tree, "nix")
+            .into_iter()
+            .filter_map(|(nix_file_path, nix_file_contents)| {
+                // Nix files that don't parse can't be edited anyway
+                let root = rnix::Root::parse(&nix_file_contents).ok().ok()?;
+                let references = find_dna_manifest_references(root.syntax());
+                Some((nix_file_path, nix_file_contents, references))
+            })
+            .collect();
+
+    for (dna_manifest_path, dna_manifest_content) in dna_yaml_files {
+        let value: serde_yaml::Value = serde_yaml::from_str(dna_manifest_content.as_str())?;
+
+        let serde_yaml::Value::Mapping(mapping) = value else {
+            return Err(Error::MalformedDnaManifest(
+                dna_manifest_path,
+                String::from("root level object is not a mapping"),
+            ));
+        };
+
+        let Some(serde_yaml::Value::String(dna_name)) = mapping.get("name") else {
+            return Err(Error::MalformedDnaManifest(
+                dna_manifest_path,
+                String::from("manifest does not have a name"),
+            ));
+        };
+
+        for (nix_file_path, nix_file_contents, references) in nix_files.iter() {
+            let mut nix_file_folder = nix_file_path.clone();
+            nix_file_folder.pop();
+
+            for reference in references {
+                let full_path = nix_file_folder.join(&reference.path).clean();
+
+                if full_path.eq(&dna_manifest_path) {
+                    nixified_dnas.push(NixifiedDna {
+                        name: dna_name.clone(),
+                        package_name: reference.package_name.clone().unwrap_or(dna_name.clone()),
+                        dna_nix: (nix_file_path.clone(), nix_file_contents.clone()),
+                        dna_manifest: (dna_manifest_path.clone(), dna_manifest_content.clone()),
+                        dna_manifest_reference_index: reference.index,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(nixified_dnas)
+}
+
+/// A `dnaManifest = ./path/to/dna.yaml;` attribute in a nix file
+struct DnaManifestReference {
+    /// The path to the manifest, relative to the nix file
+    path: String,
+    /// Offset of the path in the nix file
+    index: usize,
+    /// Name of the attribute that the DNA package is assigned to, eg. `my_dna` in `packages.my_dna = ...`
+    package_name: Option<String>,
+}
+
+fn attr_names(attrpath_value: &ast::AttrpathValue) -> Vec<String> {
+    attrpath_value
+        .attrpath()
+        .map(|attrpath| {
+            attrpath
+                .attrs()
+                .map(|attr| {
+                    attr.syntax()
+                        .text()
+                        .to_string()
+                        .trim_matches('"')
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn find_dna_manifest_references(root: &SyntaxNode) -> Vec<DnaManifestReference> {
+    root.descendants()
+        .filter_map(ast::AttrpathValue::cast)
+        .filter(|attrpath_value| attr_names(attrpath_value) == ["dnaManifest"])
+        .filter_map(|attrpath_value| {
+            let value = attrpath_value.value()?;
+            let path = value.syntax().text().to_string();
+            if !(path.starts_with("./") || path.starts_with("../")) {
+                return None;
+            }
+
+            // The package is the closest attribute that contains the call to the DNA builder
+            let package_name = attrpath_value
+                .syntax()
+                .ancestors()
+                .skip(1)
+                .find_map(ast::AttrpathValue::cast)
+                .and_then(|package| attr_names(&package).last().cloned());
+
+            Some(DnaManifestReference {
+                path,
+                index: usize::from(value.syntax().text_range().start()),
+                package_name,
+            })
+        })
+        .collect()
+}
+
+fn parse_nix_file(nix_file_path: &PathBuf, nix_file_contents: &str) -> Result<SyntaxNode, Error> {
+    let root = rnix::Root::parse(nix_file_contents)
+        .ok()
+        .map_err(|err| Error::MalformedNixFile(nix_file_path.clone(), format!("{err:?}")))?;
+    Ok(root.syntax().clone())
+}
+
+/// Finds the attribute set that builds the DNA, which contains the `dnaManifest` reference at the given offset
+fn find_dna_attr_set(root: &SyntaxNode, dna_manifest_reference_index: usize) -> Option<SyntaxNode> {
+    root.descendants()
+        .filter_map(ast::AttrpathValue::cast)
+        .find(|attrpath_value| {
+            attr_names(attrpath_value) == ["dnaManifest"]
+                && attrpath_value.value().is_some_and(|value| {
+                    usize::from(value.syntax().text_range().start()) == dna_manifest_reference_index
+                })
+        })
+        .and_then(|attrpath_value| attrpath_value.syntax().parent())
+}
+
+/// Finds the expression for the `zomes` of the given DNA attribute set,
+/// following `inherit zomes;` to the enclosing `let` or attribute set that defines them
+fn find_zomes_expression(
+    dna_nix_path: &PathBuf,
+    dna_attr_set: &SyntaxNode,
+) -> Result<SyntaxNode, Error> {
+    let zomes_binding = |node: &SyntaxNode| {
+        node.children()
+            .filter_map(ast::AttrpathValue::cast)
+            .find(|attrpath_value| attr_names(attrpath_value) == ["zomes"])
+            .and_then(|attrpath_value| attrpath_value.value())
+            .map(|value| value.syntax().clone())
+    };
+
+    if let Some(zomes) = zomes_binding(dna_attr_set) {
+        return Ok(zomes);
+    }
+
+    let zomes_inherit = dna_attr_set
+        .children()
+        .filter_map(ast::Inherit::cast)
+        .find(|inherit| {
+            inherit
+                .attrs()
+                .any(|attr| attr.syntax().text().to_string().eq("zomes"))
+        })
+        .ok_or(Error::ZomesPropertyNotFound(dna_nix_path.clone()))?;
+
+    if zomes_inherit.from().is_some() {
+        return Err(Error::UnsupportedZomesProperty(dna_nix_path.clone()));
+    }
+
+    dna_attr_set
+        .ancestors()
+        .skip(1)
+        .filter(|node| ast::LetIn::can_cast(node.kind()) || ast::AttrSet::can_cast(node.kind()))
+        .find_map(|node| zomes_binding(&node))
+        .ok_or(Error::ZomesPropertyNotFound(dna_nix_path.clone()))
+}
+
+/// Returns the attribute sets that make up the given zomes expression, going through parentheses
+/// and `//` merges, from left to right
+fn find_zomes_attr_sets(zomes: &SyntaxNode) -> Vec<SyntaxNode> {
+    if ast::AttrSet::can_cast(zomes.kind()) {
+        return vec![zomes.clone()];
+    }
+    if ast::Paren::can_cast(zomes.kind()) {
+        return zomes
+            .children()
+            .flat_map(|child| find_zomes_attr_sets(&child))
+            .collect();
+    }
+    let is_merge = ast::BinOp::can_cast(zomes.kind())
+        && zomes
+            .children_with_tokens()
+            .filter_map(|child| child.into_token())
+            .any(|token| token.text() == "//");
+    if is_merge {
+        return zomes
+            .children()
+            .flat_map(|child| find_zomes_attr_sets(&child))
+            .collect();
+    }
+    vec![]
+}
+
+/// Where to add the new zomes in the nix file that builds a DNA
+enum ZomesInsertionPoint {
+    /// Right after the opening brace of the attribute set with the zomes
+    AttrSet { index: usize, indentation: String },
+    /// At the end of a zomes expression that is not an attribute set, to merge it with a new one
+    Merge { index: usize, indentation: String },
+}
+
+/// Leading whitespace of the line that contains the given offset
+fn line_indentation(contents: &str, index: usize) -> String {
+    let line_start = contents[..index].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    contents[line_start..]
+        .chars()
+        .take_while(|c| c.eq(&' ') || c.eq(&'\t'))
+        .collect()
+}
+
+fn find_zomes_insertion_point(
+    nixified_dna: &NixifiedDna,
+    dna_nix_contents: &str,
+) -> Result<ZomesInsertionPoint, Error> {
+    let dna_nix_path = &nixified_dna.dna_nix.0;
+    let root = parse_nix_file(dna_nix_path, dna_nix_contents)?;
+    let dna_attr_set = find_dna_attr_set(&root, nixified_dna.dna_manifest_reference_index)
+        .ok_or(Error::ZomesPropertyNotFound(dna_nix_path.clone()))?;
+    let zomes = find_zomes_expression(dna_nix_path, &dna_attr_set)?;
+
+    // The last attribute set of a `//` merge takes precedence, so the new zomes go there
+    let opening_brace = find_zomes_attr_sets(&zomes).last().and_then(|attr_set| {
+        attr_set
+            .children_with_tokens()
+            .filter_map(|child| child.into_token())
+            .find(|token| token.text() == "{")
+    });
+
+    Ok(match opening_brace {
+        Some(opening_brace) => {
+            let index = usize::from(opening_brace.text_range().end());
+            ZomesInsertionPoint::AttrSet {
+                index,
+                indentation: line_indentation(dna_nix_contents, index),
+            }
+        }
+        None => {
+            let index = usize::from(zomes.text_range().end());
+            ZomesInsertionPoint::Merge {
+                index,
+                indentation: line_indentation(dna_nix_contents, index),
+            }
+        }
+    })
+}
+
+/// Returns the names of the zomes in the `zomes` of the nix file that builds the DNA
+fn find_zomes_in_dna_nix(nixified_dna: &NixifiedDna) -> Result<Vec<String>, Error> {
+    let dna_nix_path = &nixified_dna.dna_nix.0;
+    let root = parse_nix_file(dna_nix_path, &nixified_dna.dna_nix.1)?;
+    let Some(dna_attr_set) = find_dna_attr_set(&root, nixified_dna.dna_manifest_reference_index)
+    else {
+        return Ok(vec![]);
+    };
+    let zomes = find_zomes_expression(dna_nix_path, &dna_attr_set)?;
+
+    let mut zome_names: Vec<String> = vec![];
+    for attr_set in find_zomes_attr_sets(&zomes) {
+        for child in attr_set.children() {
+            if let Some(attrpath_value) = ast::AttrpathValue::cast(child.clone()) {
+                zome_names.extend(attr_names(&attrpath_value).first().cloned());
+            } else if let Some(inherit) = ast::Inherit::cast(child) {
+                zome_names.extend(inherit.attrs().map(|attr| attr.syntax().text().to_string()));
+            }
+        }
+    }
+
+    Ok(zome_names)
+}
+
+pub fn get_or_choose_nixified_dna(
+    file_tree: &FileTree,
+    dna_name: Option<String>,
+    prompt: &String,
+    chooser: &dyn Chooser,
+) -> Result<NixifiedDna, Error> {
+    let nixified_dnas = find_nixified_dnas(file_tree)?;
+
+    match nixified_dnas.len() {
+        0 => Err(Error::NoDnasFound),
+        1 => {
+            let nixified_dna = nixified_dnas.first().unwrap();
+            if let Some(dna_name) = dna_name {
+                if !nixified_dna.name.eq(&dna_name) {
+                    return Err(Error::DnaNotFound(dna_name));
+                }
+            }
+            Ok(nixified_dna.clone())
+        }
+        _ => {
+            let dna_names: Vec<String> = nixified_dnas.iter().map(|dna| dna.name.clone()).collect();
+
+            let dna_index = match dna_name {
+                Some(dna_name) => dna_names
+                    .iter()
+                    .position(|name| name.eq(&dna_name))
+                    .ok_or(Error::DnaNotFound(dna_name.clone()))?,
+                None => chooser.choose(prompt, &dna_names, 0)?,
+            };
+
+            Ok(nixified_dnas[dna_index].clone())
+        }
+    }
+}
+
+/// Returns the names of the zomes that are already in the DNA, either in the `zomes = {` property
+/// of the nix file that builds it or in its dna.yaml
+pub fn find_zomes_in_nixified_dna(nixified_dna: &NixifiedDna) -> Result<BTreeSet<String>, Error> {
+    let mut zome_names: BTreeSet<String> = BTreeSet::new();
+
+    zome_names.extend(find_zomes_in_dna_nix(nixified_dna)?);
+
+    let dna_manifest: DnaManifest = serde_yaml::from_str(&nixified_dna.dna_manifest.1)?;
+    let DnaManifest::V1(dna_manifest) = dna_manifest;
+    for zome in dna_manifest
+        .integrity
+        .zomes
+        .iter()
+        .chain(dna_manifest.coordinator.zomes.iter())
+    {
+        zome_names.insert(zome.name.to_string());
+    }
+
+    Ok(zome_names)
+}
+
+/// Adds the given zomes both to the `zomes = {` property of the nix file that builds the DNA
+/// and to its dna.yaml
+///
+/// `coordinator_dependency` is the integrity zome that the coordinator zome depends on, which is usually
+/// the integrity zome being added, but can be one that is already in the DNA
+///
+/// `zome_packages` is the nix expression for the attribute set that contains the zome packages,
+/// eg. `inputs'.profiles.packages` or `self'.packages`
+pub fn add_zome_to_nixified_dna(
+    file_tree: &mut FileTree,
+    nixified_dna: &NixifiedDna,
+    integrity_zome_name: Option<String>,
+    coordinator_zome_name: Option<String>,
+    coordinator_dependency: Option<String>,
+    zome_packages: &String,
+) -> Result<(), Error> {
+    if integrity_zome_name.is_none() && coordinator_zome_name.is_none() {
+        return Err(Error::NoZomesSpecified);
+    }
+
+    let existing_zomes = find_zomes_in_nixified_dna(nixified_dna)?;
+    for zome_name in [&integrity_zome_name, &coordinator_zome_name]
+        .into_iter()
+        .flatten()
+    {
+        if existing_zomes.contains(zome_name) {
+            return Err(Error::ZomeAlreadyExists(
+                zome_name.clone(),
+                nixified_dna.name.clone(),
+            ));
+        }
+    }
+
+    let zomes_to_add: Vec<String> = [&integrity_zome_name, &coordinator_zome_name]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+
+    map_file(
+        file_tree,
+        &nixified_dna.dna_nix.0.clone(),
+        |mut dna_contents| {
+            let zome_lines = |indentation: &String| -> String {
+                zomes_to_add
+                    .iter()
+                    .map(|name| format!("\n{indentation}  {name} = {zome_packages}.{name};"))
+                    .collect()
+            };
+
+            match find_zomes_insertion_point(nixified_dna, &dna_contents)? {
+                ZomesInsertionPoint::AttrSet { index, indentation } => {
+                    dna_contents.insert_str(index, &zome_lines(&indentation));
+                }
+                ZomesInsertionPoint::Merge { index, indentation } => {
+                    dna_contents.insert_str(
+                        index,
+                        &format!(" // {{{}\n{indentation}}}", zome_lines(&indentation)),
+                    );
+                }
+            }
+
+            Ok::<String, Error>(dna_contents)
+        },
+    )?;
+
+    eprintln!("Added the integrity zome {integrity_zome_name:?} and the coordinator zome {coordinator_zome_name:?} to {:?}.", nixified_dna.dna_nix.0);
+
+    let mut dna_manifest = nixified_dna.dna_manifest.1.clone();
+    if let Some(integrity_zome) = integrity_zome_name.clone() {
+        dna_manifest = add_zome_to_dna_manifest_section(
+            &nixified_dna.dna_manifest.0,
+            &dna_manifest,
+            "integrity",
+            zome_manifest_entry(&integrity_zome, None),
+        )?;
+    }
+    if let Some(coordinator_zome) = coordinator_zome_name.clone() {
+        dna_manifest = add_zome_to_dna_manifest_section(
+            &nixified_dna.dna_manifest.0,
+            &dna_manifest,
+            "coordinator",
+            zome_manifest_entry(&coordinator_zome, coordinator_dependency.as_ref()),
+        )?;
+    }
+    // Make sure that the edited manifest is still valid
+    let _: DnaManifest = serde_yaml::from_str(&dna_manifest)?;
+
+    insert_file(file_tree, &nixified_dna.dna_manifest.0, &dna_manifest)?;
+
+    eprintln!("Added the integrity zome {integrity_zome_name:?} and the coordinator zome {coordinator_zome_name:?} to {:?}.", nixified_dna.dna_manifest.0);
+
+    Ok(())
+}
+
+/// Removes the zomes whose packages come from the given attribute set both from the nix file
+/// that builds the DNA and from its dna.yaml, returning the names of the removed zomes
+///
+/// This reverses what `add_zome_to_nixified_dna` does with the same `zome_packages`
+pub fn remove_zomes_from_nixified_dna(
+    file_tree: &mut FileTree,
+    nixified_dna: &NixifiedDna,
+    zome_packages: &String,
+) -> Result<Vec<String>, Error> {
+    let zome_re = Regex::new(&format!(
+        r"(?m)^[ \t]*(?<zomeName>[\w-]+)[ \t]*=[ \t]*{}\.[\w-]+;[ \t]*\n",
+        regex::escape(zome_packages)
+    ))?;
+
+    let zome_names: Vec<String> = zome_re
+        .captures_iter(&nixified_dna.dna_nix.1)
+        .map(|captures| captures["zomeName"].to_string())
+        .collect();
+
+    if zome_names.is_empty() {
+        return Ok(zome_names);
+    }
+
+    map_file(file_tree, &nixified_dna.dna_nix.0, |dna_contents| {
+        Ok::<String, Error>(zome_re.replace_all(&dna_contents, "").to_string())
+    })?;
+
+    eprintln!(
+        "Removed the zomes {zome_names:?} from {:?}.",
+        nixified_dna.dna_nix.0
+    );
+
+    let dna_manifest_content = file_content(file_tree, &nixified_dna.dna_manifest.0)?;
+
+    let mut dna_manifest = dna_manifest_content.clone();
+    for section in ["integrity", "coordinator"] {
+        dna_manifest = remove_zomes_from_dna_manifest_section(
+            &nixified_dna.dna_manifest.0,
+            &dna_manifest,
+            section,
+            &zome_names,
+        )?;
+    }
+
+    if dna_manifest.eq(&dna_manifest_content) {
+        return Ok(zome_names);
+    }
+    // Make sure that the edited manifest is still valid
+    let _: DnaManifest = serde_yaml::from_str(&dna_manifest)?;
+
+    insert_file(file_tree, &nixified_dna.dna_manifest.0, &dna_manifest)?;
+
+    eprintln!(
+        "Removed the zomes {zome_names:?} from {:?}.",
+        nixified_dna.dna_manifest.0
+    );
+
+    Ok(zome_names)
+}
+
+/// Lines of the entry for a bundled zome in a dna.yaml zomes list, relative to the indentation of the list
+fn zome_manifest_entry(zome_name: &String, dependency: Option<&String>) -> Vec<String> {
+    let mut lines = vec![
+        format!("- name: {zome_name}"),
+        String::from("  hash: ~"),
+        String::from("  bundled: <NIX_PACKAGE>"),
+    ];
+    match dependency {
+        Some(dependency) => {
+            lines.push(String::from("  dependencies:"));
+            lines.push(format!("  - name: {dependency}"));
+        }
+        None => lines.push(String::from("  dependencies: ~")),
+    }
+    lines.push(String::from("  dylib: ~"));
+    lines
+}
+
+/// Location of the `zomes` list of the integrity or coordinator section of a dna.yaml,
+/// as indexes of its lines
+struct DnaManifestZomes {
+    /// Line of the `zomes:` key
+    key_line: usize,
+    /// Indentation of the `-` of the list items
+    items_indent: usize,
+    /// Lines of each of the items, with the end excluded
+    items: Vec<(usize, usize)>,
+    /// Whether the list was written as `[]` or `~` in the key line
+    inline_empty: bool,
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Removes the trailing comment of the given yaml line
+fn strip_yaml_comment(value: &str) -> &str {
+    match value.find(" #") {
+        Some(index) => &value[..index],
+        None => value,
+    }
+    .trim()
+}
+
+fn find_dna_manifest_zomes(
+    dna_manifest_path: &PathBuf,
+    lines: &Vec<String>,
+    section: &str,
+) -> Result<DnaManifestZomes, Error> {
+    let not_found = || {
+        Error::MalformedDnaManifest(
+            dna_manifest_path.clone(),
+            format!("no {section} zomes list was found"),
+        )
+    };
+
+    let section_line = lines
+        .iter()
+        .position(|line| indentation(line) == 0 && line.starts_with(&format!("{section}:")))
+        .ok_or_else(not_found)?;
+    let section_end = (section_line + 1..lines.len())
+        .find(|i| !is_blank_or_comment(&lines[*i]) && indentation(&lines[*i]) == 0)
+        .unwrap_or(lines.len());
+
+    let children_indent = (section_line + 1..section_end)
+        .find(|i| !is_blank_or_comment(&lines[*i]))
+        .map(|i| indentation(&lines[i]))
+        .ok_or_else(not_found)?;
+    let key_line = (section_line + 1..section_end)
+        .find(|i| {
+            indentation(&lines[*i]) == children_indent
+                && lines[*i].trim_start().starts_with("zomes:")
+        })
+        .ok_or_else(not_found)?;
+
+    let value = strip_yaml_comment(&lines[key_line].trim_start()["zomes:".len()..]);
+    match value {
+        "[]" | "~" | "null" => {
+            return Ok(DnaManifestZomes {
+                key_line,
+                items_indent: children_indent,
+                items: vec![],
+                inline_empty: true,
+            })
+        }
+        "" => {}
+        _ => {
+            return Err(Error::MalformedDnaManifest(
+                dna_manifest_path.clone(),
+                format!("the {section} zomes can only be edited if they are a block list"),
+            ))
+        }
+    }
+
+    let mut items_indent: Option<usize> = None;
+    let mut items: Vec<(usize, usize)> = vec![];
+
+    for i in key_line + 1..section_end {
+        let line = &lines[i];
+        if is_blank_or_comment(line) {
+            continue;
+        }
+        let indent = indentation(line);
+        let trimmed = line.trim_start();
+        let is_item_start = (trimmed.starts_with("- ") || trimmed.eq("-"))
+            && indent >= children_indent
+            && items_indent
+                .map(|items_indent| items_indent == indent)
+                .unwrap_or(true);
+
+        if is_item_start {
+            items_indent = Some(indent);
+            items.push((i, i + 1));
+        } else if items_indent.is_some_and(|items_indent| indent > items_indent) {
+            items.last_mut().unwrap().1 = i + 1;
+        } else {
+            break;
+        }
+    }
+
+    Ok(DnaManifestZomes {
+        key_line,
+        items_indent: items_indent.unwrap_or(children_indent),
+        items,
+        inline_empty: false,
+    })
+}
+
+/// Returns the name of the zome declared in the given lines of a zomes list item
+fn zome_manifest_entry_name(item_lines: &[String], items_indent: usize) -> Option<String> {
+    let first_line = item_lines
+        .first()?
+        .trim_start()
+        .trim_start_matches('-')
+        .trim_start();
+
+    let name_value = match first_line.strip_prefix("name:") {
+        Some(value) => value,
+        None => item_lines.iter().skip(1).find_map(|line| {
+            match indentation(line) == items_indent + 2 {
+                true => line.trim_start().strip_prefix("name:"),
+                false => None,
+            }
+        })?,
+    };
+
+    Some(
+        strip_yaml_comment(name_value)
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string(),
+    )
+}
+
+fn split_lines(content: &String) -> Vec<String> {
+    content
+        .split_inclusive('\n')
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Appends the given entry to the zomes list of the given section of the dna.yaml,
+/// leaving the rest of the file untouched
+fn add_zome_to_dna_manifest_section(
+    dna_manifest_path: &PathBuf,
+    dna_manifest: &String,
+    section: &str,
+    entry: Vec<String>,
+) -> Result<String, Error> {
+    let mut lines = split_lines(dna_manifest);
+    let zomes = find_dna_manifest_zomes(dna_manifest_path, &lines, section)?;
+
+    if zomes.inline_empty {
+        let key_line = &lines[zomes.key_line];
+        let comment = key_line
+            .find(" #")
+            .map(|index| key_line[index..].trim_end().to_string());
+        lines[zomes.key_line] = format!(
+            "{}zomes:{}\n",
+            " ".repeat(indentation(key_line)),
+            comment.unwrap_or_default()
+        );
+    }
+
+    let insert_at = zomes
+        .items
+        .last()
+        .map(|(_, end)| *end)
+        .unwrap_or(zomes.key_line + 1);
+    if let Some(previous_line) = lines.get_mut(insert_at - 1) {
+        if !previous_line.ends_with('\n') {
+            previous_line.push('\n');
+        }
+    }
+
+    let indent = " ".repeat(zomes.items_indent);
+    let entry_lines = entry.into_iter().map(|line| format!("{indent}{line}\n"));
+
+    lines.splice(insert_at..insert_at, entry_lines);
+
+    Ok(lines.join(""))
+}
+
+/// Removes the entries for the given zomes from the zomes list of the given section of the dna.yaml,
+/// leaving the rest of the file untouched
+fn remove_zomes_from_dna_manifest_section(
+    dna_manifest_path: &PathBuf,
+    dna_manifest: &String,
+    section: &str,
+    zome_names: &Vec<String>,
+) -> Result<String, Error> {
+    let mut lines = split_lines(dna_manifest);
+    let zomes = find_dna_manifest_zomes(dna_manifest_path, &lines, section)?;
+
+    let items_to_remove: Vec<(usize, usize)> = zomes
+        .items
+        .iter()
+        .filter(|(start, end)| {
+            zome_manifest_entry_name(&lines[*start..*end], zomes.items_indent)
+                .is_some_and(|name| zome_names.contains(&name))
+        })
+        .cloned()
+        .collect();
+
+    if items_to_remove.is_empty() {
+        return Ok(dna_manifest.clone());
+    }
+
+    // Remove from the end so that the indexes of the previous items stay valid
+    for (start, end) in items_to_remove.iter().rev() {
+        lines.drain(*start..*end);
+    }
+
+    if items_to_remove.len() == zomes.items.len() {
+        let key_line = &lines[zomes.key_line];
+        lines[zomes.key_line] = key_line.replacen("zomes:", "zomes: []", 1);
+    }
+
+    Ok(lines.join(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use build_fs_tree::{dir, file};
+
+    #[test]
+    fn edit_dna_manifest_preserving_format_test() {
+        let path = PathBuf::from("dna.yaml");
+        let dna_manifest = String::from(
+            r#"---
+manifest_version: "1"
+name: forum # the forum DNA
+integrity:
+  network_seed: ~
+  properties: ~
+  origin_time: 1709638576394039
+  zomes:
+    # posts
+    - name: posts_integrity
+      hash: ~
+      bundled: ../../target/wasm32-unknown-unknown/release/posts_integrity.wasm
+      dependencies: ~
+      dylib: ~
+coordinator:
+  zomes: [] # no coordinators yet
+"#,
+        );
+
+        let added = add_zome_to_dna_manifest_section(
+            &path,
+            &dna_manifest,
+            "integrity",
+            zome_manifest_entry(&String::from("profiles_integrity"), None),
+        )
+        .unwrap();
+        let added = add_zome_to_dna_manifest_section(
+            &path,
+            &added,
+            "coordinator",
+            zome_manifest_entry(
+                &String::from("profiles"),
+                Some(&String::from("profiles_integrity")),
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            added,
+            r#"---
+manifest_version: "1"
+name: forum # the forum DNA
+integrity:
+  network_seed: ~
+  properties: ~
+  origin_time: 1709638576394039
+  zomes:
+    # posts
+    - name: posts_integrity
+      hash: ~
+      bundled: ../../target/wasm32-unknown-unknown/release/posts_integrity.wasm
+      dependencies: ~
+      dylib: ~
+    - name: profiles_integrity
+      hash: ~
+      bundled: <NIX_PACKAGE>
+      dependencies: ~
+      dylib: ~
+coordinator:
+  zomes: # no coordinators yet
+  - name: profiles
+    hash: ~
+    bundled: <NIX_PACKAGE>
+    dependencies:
+    - name: profiles_integrity
+    dylib: ~
+"#
+        );
+
+        let zome_names = vec![String::from("profiles_integrity"), String::from("profiles")];
+        let removed =
+            remove_zomes_from_dna_manifest_section(&path, &added, "integrity", &zome_names)
+                .unwrap();
+        let removed =
+            remove_zomes_from_dna_manifest_section(&path, &removed, "coordinator", &zome_names)
+                .unwrap();
+
+        assert_eq!(removed, dna_manifest);
+    }
+
+    #[test]
+    fn add_zomes_to_inherited_and_merged_zomes_test() {
+        let dna_yaml = |name: &str| {
+            format!(
+                r#"---
+manifest_version: "1"
+name: {name}
+integrity:
+  network_seed: ~
+  properties: ~
+  origin_time: 1709638576394039
+  zomes: []
+coordinator:
+  zomes: []
+"#
+            )
+        };
+        let mut file_tree: FileTree = dir! {
+            "dnas.nix" => file!(String::from(r#"{ inputs, ... }:
+
+{
+  perSystem = { inputs', self', system, ... }:
+    let
+      zomes = {
+      };
+    in {
+      packages.first_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        dnaManifest = ./first/dna.yaml;
+        inherit zomes;
+      };
+      packages.second_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        zomes = inputs'.other.packages.zomes // {
+        };
+        dnaManifest = ./second/dna.yaml;
+      };
+    };
+}
+"#)),
+            "first" => dir! {
+                "dna.yaml" => file!(dna_yaml("first")),
+            },
+            "second" => dir! {
+                "dna.yaml" => file!(dna_yaml("second")),
+            },
+        };
+
+        let dnas = find_nixified_dnas(&file_tree).unwrap();
+        assert_eq!(
+            dnas.iter()
+                .map(|dna| dna.package_name.clone())
+                .collect::<Vec<String>>(),
+            vec![String::from("first_dna"), String::from("second_dna")]
+        );
+
+        add_zome_to_nixified_dna(
+            &mut file_tree,
+            &dnas[1],
+            Some("posts_integrity".into()),
+            Some("posts".into()),
+            Some("posts_integrity".into()),
+            &String::from("self'.packages"),
+        )
+        .unwrap();
+
+        let dnas = find_nixified_dnas(&file_tree).unwrap();
+        add_zome_to_nixified_dna(
+            &mut file_tree,
+            &dnas[0],
+            None,
+            Some("comments".into()),
+            None,
+            &String::from("self'.packages"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&file_tree, PathBuf::from("dnas.nix").as_path()).unwrap(),
+            r#"{ inputs, ... }:
+
+{
+  perSystem = { inputs', self', system, ... }:
+    let
+      zomes = {
+        comments = self'.packages.comments;
+      };
+    in {
+      packages.first_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        dnaManifest = ./first/dna.yaml;
+        inherit zomes;
+      };
+      packages.second_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        zomes = inputs'.other.packages.zomes // {
+          posts_integrity = self'.packages.posts_integrity;
+          posts = self'.packages.posts;
+        };
+        dnaManifest = ./second/dna.yaml;
+      };
+    };
+}
+"#
+        );
+    }
+}
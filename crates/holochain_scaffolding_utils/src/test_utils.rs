@@ -0,0 +1,42 @@
+//! Fixtures for the tests of the crates that scaffold into holochain repositories
+
+/// A dna.yaml manifest for the given DNA, without any zomes
+pub fn empty_dna_yaml(dna_name: &str) -> String {
+    format!(
+        r#"
+---
+manifest_version: "1"
+name: {dna_name}
+integrity:
+  network_seed: ~
+  properties: ~
+  origin_time: 1709638576394039
+  zomes: []
+coordinator:
+  zomes: []
+"#
+    )
+}
+
+/// A dna.nix declaring the `my_dna` package, built from `./dna.yaml` without any zomes
+pub fn empty_dna_nix() -> String {
+    String::from(
+        r#"{ inputs, ... }:
+
+{
+  perSystem =
+    { inputs'
+    , self'
+    , system
+    , ...
+    }: {
+      packages.my_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        dnaManifest = ./dna.yaml;
+        zomes = {
+        };
+      };
+    };
+}
+"#,
+    )
+}
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use file_tree_utils::{file_content, map_file, FileTree, FileTreeError};
 use regex::{Captures, Regex};
+use rnix::{ast, SyntaxNode};
+use rowan::ast::AstNode;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -14,6 +16,12 @@ pub enum NixScaffoldingUtilsError {
 
     #[error("flake.nix is malformed")]
     MalformedFlakeNixError,
+
+    #[error("No \"imports = [\" property was found in flake.nix")]
+    ImportsNotFoundError,
+
+    #[error("The flake input {0} was not found in flake.nix")]
+    FlakeInputNotFoundError(String),
 }
 
 pub fn add_flake_input(
@@ -35,7 +43,7 @@ pub fn add_flake_input(
         },
     )?;
 
-    println!("Added flake input {input_name} to flake.nix.");
+    eprintln!("Added flake input {input_name} to flake.nix.");
 
     Ok(file_tree)
 }
@@ -67,6 +75,264 @@ pub fn add_flake_input_to_flake_file(
     }
 }
 
+pub fn remove_flake_input(
+    mut file_tree: FileTree,
+    input_name: String,
+) -> Result<FileTree, NixScaffoldingUtilsError> {
+    let flake_nix_path = PathBuf::from("flake.nix");
+
+    map_file(
+        &mut file_tree,
+        flake_nix_path.as_path(),
+        |flake_nix_contents| remove_flake_input_from_flake_file(flake_nix_contents, &input_name),
+    )?;
+
+    eprintln!("Removed flake input {input_name} from flake.nix.");
+
+    Ok(file_tree)
+}
+
+/// Removes all the attributes that declare the given input: the ones in the `inputs = { ... };` attribute set,
+/// like `{input_name}.url = "...";` or `{input_name} = { url = "..."; inputs.nixpkgs.follows = "..."; };`,
+/// and the ones at the root of the flake, like `inputs.{input_name}.inputs.nixpkgs.follows = "...";`
+pub fn remove_flake_input_from_flake_file(
+    flake_nix: String,
+    input_name: &String,
+) -> Result<String, NixScaffoldingUtilsError> {
+    let flake = flake_attr_set(&flake_nix)?;
+
+    let mut input_attributes: Vec<SyntaxNode> = vec![];
+    for attribute in flake.children().filter_map(ast::AttrpathValue::cast) {
+        let names = attr_names(&attribute);
+        if names.len() > 1 && names[0] == "inputs" && names[1].eq(input_name) {
+            input_attributes.push(attribute.syntax().clone());
+        } else if names == ["inputs"] {
+            let Some(inputs) = attribute
+                .value()
+                .map(|value| value.syntax().clone())
+                .filter(|value| ast::AttrSet::can_cast(value.kind()))
+            else {
+                continue;
+            };
+            input_attributes.extend(
+                inputs
+                    .children()
+                    .filter_map(ast::AttrpathValue::cast)
+                    .filter(|input| attr_names(input).first() == Some(input_name))
+                    .map(|input| input.syntax().clone()),
+            );
+        }
+    }
+
+    if input_attributes.is_empty() {
+        return Err(NixScaffoldingUtilsError::FlakeInputNotFoundError(
+            input_name.clone(),
+        ));
+    }
+
+    // Remove the attributes from the end of the file, so that the positions of the remaining ones stay valid
+    let mut new_flake_nix = flake_nix.clone();
+    for attribute in input_attributes.into_iter().rev() {
+        let range = attribute.text_range();
+        let (start, end) = removal_range(
+            &new_flake_nix,
+            usize::from(range.start()),
+            usize::from(range.end()),
+        );
+        new_flake_nix.replace_range(start..end, "");
+    }
+
+    Ok(new_flake_nix)
+}
+
+/// The attribute set at the root of the flake.nix
+fn flake_attr_set(flake_nix: &String) -> Result<SyntaxNode, NixScaffoldingUtilsError> {
+    let Ok(root) = rnix::Root::parse(flake_nix).ok() else {
+        return Err(NixScaffoldingUtilsError::MalformedFlakeNixError);
+    };
+    root.syntax()
+        .children()
+        .find(|node| ast::AttrSet::can_cast(node.kind()))
+        .ok_or(NixScaffoldingUtilsError::MalformedFlakeNixError)
+}
+
+/// Finds the value of the attribute with the given path in the given attribute set, following the nested
+/// attribute sets, so that `inputs.a.url` is found in `inputs.a.url = ...`, `inputs = { a.url = ...; }`
+/// or `inputs = { a = { url = ...; }; }`
+fn find_attribute_value(attr_set: &SyntaxNode, path: &[String]) -> Option<SyntaxNode> {
+    attr_set
+        .children()
+        .filter_map(ast::AttrpathValue::cast)
+        .find_map(|attribute| {
+            let names = attr_names(&attribute);
+            if names.is_empty() || !path.starts_with(&names) {
+                return None;
+            }
+            let value = attribute.value()?.syntax().clone();
+            if names.len() == path.len() {
+                return Some(value);
+            }
+            if !ast::AttrSet::can_cast(value.kind()) {
+                return None;
+            }
+            find_attribute_value(&value, &path[names.len()..])
+        })
+}
+
+/// The string node with the URL of the given input in the flake.nix, if it exists
+fn find_flake_input_url(
+    flake_nix: &String,
+    input_name: &String,
+) -> Result<Option<SyntaxNode>, NixScaffoldingUtilsError> {
+    let flake = flake_attr_set(flake_nix)?;
+    let path = [
+        String::from("inputs"),
+        input_name.clone(),
+        String::from("url"),
+    ];
+
+    Ok(find_attribute_value(&flake, &path).filter(|value| ast::Str::can_cast(value.kind())))
+}
+
+fn attr_names(attrpath_value: &ast::AttrpathValue) -> Vec<String> {
+    attrpath_value
+        .attrpath()
+        .map(|attrpath| {
+            attrpath
+                .attrs()
+                .map(|attr| {
+                    attr.syntax()
+                        .text()
+                        .to_string()
+                        .trim_matches('"')
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Extends the range of an attribute to the whole lines that it spans if nothing else is in them,
+/// or to the whitespace that follows it otherwise
+fn removal_range(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = text[end..]
+        .find('\n')
+        .map(|i| end + i + 1)
+        .unwrap_or(text.len());
+
+    if text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty() {
+        return (line_start, line_end);
+    }
+
+    let following_whitespace =
+        text[end..].len() - text[end..].trim_start_matches([' ', '\t']).len();
+    (start, end + following_whitespace)
+}
+
+/// Returns the URL of the given input in the flake.nix, if it exists
+pub fn get_flake_input_url(
+    flake_nix: &String,
+    input_name: &String,
+) -> Result<Option<String>, NixScaffoldingUtilsError> {
+    let url = find_flake_input_url(flake_nix, input_name)?;
+
+    Ok(url.map(|url| url.text().to_string().trim_matches('"').to_string()))
+}
+
+pub fn set_flake_input_url(
+    mut file_tree: FileTree,
+    input_name: String,
+    input_ref: String,
+) -> Result<FileTree, NixScaffoldingUtilsError> {
+    let flake_nix_path = PathBuf::from("flake.nix");
+
+    map_file(
+        &mut file_tree,
+        flake_nix_path.as_path(),
+        |flake_nix_contents| {
+            set_flake_input_url_in_flake_file(flake_nix_contents, &input_name, &input_ref)
+        },
+    )?;
+
+    eprintln!("Set the url of the flake input {input_name} to {input_ref} in flake.nix.");
+
+    Ok(file_tree)
+}
+
+pub fn set_flake_input_url_in_flake_file(
+    flake_nix: String,
+    input_name: &String,
+    input_ref: &String,
+) -> Result<String, NixScaffoldingUtilsError> {
+    let Some(url) = find_flake_input_url(&flake_nix, input_name)? else {
+        return Err(NixScaffoldingUtilsError::FlakeInputNotFoundError(
+            input_name.clone(),
+        ));
+    };
+
+    let range = url.text_range();
+    let mut new_flake_nix = flake_nix.clone();
+    new_flake_nix.replace_range(
+        usize::from(range.start())..usize::from(range.end()),
+        &format!("\"{input_ref}\""),
+    );
+
+    Ok(new_flake_nix)
+}
+
+pub fn add_flake_import(
+    mut file_tree: FileTree,
+    import_path: String,
+) -> Result<FileTree, NixScaffoldingUtilsError> {
+    let flake_nix_path = PathBuf::from("flake.nix");
+    let flake_nix_contents = file_content(&file_tree, flake_nix_path.as_path())?;
+    let Ok(_root) = rnix::Root::parse(&flake_nix_contents).ok() else {
+        return Err(NixScaffoldingUtilsError::MalformedFlakeNixError);
+    };
+
+    map_file(
+        &mut file_tree,
+        flake_nix_path.as_path(),
+        |flake_nix_contents| add_import_to_flake_file(flake_nix_contents, import_path.clone()),
+    )?;
+
+    eprintln!("Added import {import_path} to flake.nix.");
+
+    Ok(file_tree)
+}
+
+pub fn add_import_to_flake_file(
+    flake_nix: String,
+    import_path: String,
+) -> Result<String, NixScaffoldingUtilsError> {
+    let re = Regex::new(
+        r"imports(?<white1>\s*)=(?<white2>\s*)\[(?<rest_of_line>[^\n]*)\n(?<indentation>[ \t]*)(?<next>\S?)",
+    )?;
+
+    let Some(caps) = re.captures(&flake_nix) else {
+        return Err(NixScaffoldingUtilsError::ImportsNotFoundError);
+    };
+
+    let mut new_flake_nix = flake_nix.clone();
+
+    if caps["rest_of_line"].trim().is_empty() {
+        // Multiline list: add the import in its own line, with the same indentation as the existing items
+        let indentation = match &caps["next"] {
+            "]" => format!("{}  ", &caps["indentation"]),
+            _ => caps["indentation"].to_string(),
+        };
+        let insert_index = caps.name("rest_of_line").unwrap().end();
+        new_flake_nix.insert_str(insert_index, &format!("\n{indentation}{import_path}"));
+    } else {
+        // Inline list: add the import right after the opening bracket
+        let insert_index = caps.name("white2").unwrap().end() + 1;
+        new_flake_nix.insert_str(insert_index, &format!(" {import_path}"));
+    }
+
+    Ok(new_flake_nix)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,6 +383,203 @@ mod tests {
   inputs.someinput.url = "someurl";
   inputs.somefirstinput.url = "someurl";
   outputs = {};
+}"#
+        );
+    }
+
+    #[test]
+    fn remove_flake_input_test() {
+        let flake_contents = r#"{
+  inputs = {
+    someinput.url = "someurl";
+    someinput.inputs.nixpkgs.follows = "nixpkgs";
+    someotherinput.url = "someotherurl";
+  };
+  outputs = {};
+}"#;
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(flake_contents)
+        };
+
+        let file_tree = remove_flake_input(repo, "someinput".to_string()).unwrap();
+
+        assert_eq!(
+            file_content(&file_tree, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  inputs = {
+    someotherinput.url = "someotherurl";
+  };
+  outputs = {};
+}"#
+        );
+    }
+
+    #[test]
+    fn remove_flake_input_block_test() {
+        let flake_contents = String::from(
+            r#"{
+  inputs.someinput.inputs.nixpkgs.follows = "nixpkgs";
+  inputs = {
+    someinput = {
+      url = "someurl";
+      inputs.holonix.follows = "holonix";
+    };
+    someotherinput.url = "someotherurl";
+    someotherinput.inputs.someinput.follows = "someinput";
+    someinput.flake = false; inline = { url = "inlineurl"; };
+  };
+  outputs = {};
+}"#,
+        );
+
+        assert_eq!(
+            remove_flake_input_from_flake_file(flake_contents, &"someinput".to_string()).unwrap(),
+            r#"{
+  inputs = {
+    someotherinput.url = "someotherurl";
+    someotherinput.inputs.someinput.follows = "someinput";
+    inline = { url = "inlineurl"; };
+  };
+  outputs = {};
+}"#
+        );
+
+        assert!(matches!(
+            remove_flake_input_from_flake_file(
+                String::from("{ inputs = {}; outputs = {}; }"),
+                &"someinput".to_string()
+            ),
+            Err(NixScaffoldingUtilsError::FlakeInputNotFoundError(_))
+        ));
+    }
+
+    #[test]
+    fn set_flake_input_url_test() {
+        let flake_contents = r#"{
+  inputs.someinput.url = "github:some/input";
+  inputs.someotherinput.url = "github:someother/input";
+  outputs = {};
+}"#;
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(flake_contents)
+        };
+
+        let file_tree = set_flake_input_url(
+            repo,
+            "someinput".to_string(),
+            "github:some/input/develop".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&file_tree, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  inputs.someinput.url = "github:some/input/develop";
+  inputs.someotherinput.url = "github:someother/input";
+  outputs = {};
+}"#
+        );
+    }
+
+    #[test]
+    fn flake_input_url_block_test() {
+        let flake_nix = String::from(
+            r#"{
+  inputs = {
+    someinput = {
+      url = "github:some/input";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+    someotherinput.url = "github:someother/input";
+  };
+  outputs = {};
+}"#,
+        );
+
+        assert_eq!(
+            get_flake_input_url(&flake_nix, &"someinput".to_string()).unwrap(),
+            Some("github:some/input".to_string())
+        );
+        assert_eq!(
+            get_flake_input_url(&flake_nix, &"someotherinput".to_string()).unwrap(),
+            Some("github:someother/input".to_string())
+        );
+        assert_eq!(
+            get_flake_input_url(&flake_nix, &"nixpkgs".to_string()).unwrap(),
+            None
+        );
+
+        assert_eq!(
+            set_flake_input_url_in_flake_file(
+                flake_nix,
+                &"someinput".to_string(),
+                &"github:some/input/develop".to_string()
+            )
+            .unwrap(),
+            r#"{
+  inputs = {
+    someinput = {
+      url = "github:some/input/develop";
+      inputs.nixpkgs.follows = "nixpkgs";
+    };
+    someotherinput.url = "github:someother/input";
+  };
+  outputs = {};
+}"#
+        );
+    }
+
+    #[test]
+    fn add_flake_import_test() {
+        let flake_contents = r#"{
+  outputs = inputs@{ ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake { inherit inputs; } {
+      imports = [
+        ./dna.nix
+      ];
+    };
+}"#;
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(flake_contents)
+        };
+
+        let file_tree = add_flake_import(repo, "./happ/happ.nix".to_string()).unwrap();
+
+        assert_eq!(
+            file_content(&file_tree, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  outputs = inputs@{ ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake { inherit inputs; } {
+      imports = [
+        ./happ/happ.nix
+        ./dna.nix
+      ];
+    };
+}"#
+        );
+    }
+
+    #[test]
+    fn add_flake_import_inline_test() {
+        let flake_contents = r#"{
+  outputs = inputs@{ ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake { inherit inputs; } {
+      imports = [ ./dna/dna.nix ];
+    };
+}"#;
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(flake_contents)
+        };
+
+        let file_tree = add_flake_import(repo, "./happ/happ.nix".to_string()).unwrap();
+
+        assert_eq!(
+            file_content(&file_tree, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  outputs = inputs@{ ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake { inherit inputs; } {
+      imports = [ ./happ/happ.nix ./dna/dna.nix ];
+    };
 }"#
         );
     }
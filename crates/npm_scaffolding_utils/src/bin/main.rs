@@ -2,7 +2,8 @@ use anyhow::Result;
 use build_fs_tree::{Build, MergeableFileSystemTree};
 use clap::Parser;
 use colored::Colorize;
-use npm_scaffolding_utils::add_npm_dependency;
+use npm_scaffolding_utils::{add_npm_dependency, DependencyKind};
+use prompt_utils::build_chooser;
 use std::{ffi::OsString, path::PathBuf, process::ExitCode};
 
 /// Adds a flake input to your flake.nix.
@@ -14,10 +15,31 @@ pub struct Args {
     /// See https://docs.npmjs.com/cli/v10/configuring-npm/package-json#dependencies for all the types of sources available.
     pub dependency_source: String,
 
+    /// Add the dependency to the "devDependencies" of the package.
+    #[clap(long, conflicts_with_all = ["peer", "optional"])]
+    pub dev: bool,
+
+    /// Add the dependency to the "peerDependencies" of the package.
+    #[clap(long, conflicts_with_all = ["dev", "optional"])]
+    pub peer: bool,
+
+    /// Add the dependency to the "optionalDependencies" of the package.
+    #[clap(long, conflicts_with_all = ["dev", "peer"])]
+    pub optional: bool,
+
     /// The **local** NPM package to add the dependency to.
     #[clap(long)]
     pub package_to_add_the_dependency_to: Option<String>,
 
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[clap(long, visible_alias = "yes")]
+    pub non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[clap(long)]
+    pub answers_file: Option<PathBuf>,
+
     /// The path of the file tree to modify.
     #[clap(long, default_value = "./.")]
     pub path: PathBuf,
@@ -35,13 +57,25 @@ fn internal_main() -> Result<()> {
     let args = Args::parse();
 
     let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
+
+    let dependency_kind = if args.dev {
+        DependencyKind::Dev
+    } else if args.peer {
+        DependencyKind::Peer
+    } else if args.optional {
+        DependencyKind::Optional
+    } else {
+        DependencyKind::Regular
+    };
 
     let file_tree = add_npm_dependency(
         file_tree,
         args.dependency.clone(),
         args.dependency_source,
+        dependency_kind,
         args.package_to_add_the_dependency_to,
-        None,
+        chooser.as_ref(),
     )?;
 
     let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
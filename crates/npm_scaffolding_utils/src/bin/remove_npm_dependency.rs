@@ -0,0 +1,52 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use npm_scaffolding_utils::remove_npm_dependency;
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+/// Removes an NPM dependency from the packages of your project.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the NPM dependency package to remove from the project.
+    pub dependency: String,
+
+    /// The **local** NPM package to remove the dependency from.
+    /// If not given, the dependency is removed from all the packages that declare it.
+    #[clap(long)]
+    pub package: Option<String>,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+
+    let file_tree = remove_npm_dependency(file_tree, args.dependency.clone(), args.package)?;
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!(
+            "Successfully removed NPM dependency package {}",
+            args.dependency.bold()
+        )
+        .green()
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use npm_scaffolding_utils::update_npm_dependency_source;
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+/// Sets the source of an NPM dependency in the packages of your project that declare it.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The name of the NPM dependency package to update.
+    pub dependency: String,
+    /// The new source of the NPM dependency package.
+    /// See https://docs.npmjs.com/cli/v10/configuring-npm/package-json#dependencies for all the types of sources available.
+    pub dependency_source: String,
+
+    /// The **local** NPM package to update the dependency in.
+    /// If not given, the dependency is updated in all the packages that declare it.
+    #[clap(long)]
+    pub package: Option<String>,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+
+    let file_tree = update_npm_dependency_source(
+        file_tree,
+        args.dependency.clone(),
+        args.dependency_source.clone(),
+        args.package,
+    )?;
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!(
+            "Successfully set NPM dependency package {} to {}",
+            args.dependency.bold(),
+            args.dependency_source.bold()
+        )
+        .green()
+    );
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+
+use crate::NpmScaffoldingUtilsError;
+
+/// Git hosts that NPM dependencies can refer to with a shorthand, like `github:owner/repo`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl GitHost {
+    /// Prefix of the shorthand for this host, like `github` in `github:owner/repo`
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            GitHost::GitHub => "github",
+            GitHost::GitLab => "gitlab",
+            GitHost::Bitbucket => "bitbucket",
+        }
+    }
+}
+
+/// The source of an NPM dependency, as declared in a package.json
+///
+/// See https://docs.npmjs.com/cli/v10/configuring-npm/package-json#dependencies
+#[derive(Debug, Clone, PartialEq)]
+pub enum NpmDependencySpec {
+    /// A semver range, like `^0.300.0`, or a dist-tag, like `latest`
+    Version(String),
+    /// A local directory or tarball without the `file:` prefix, like `../ui`
+    Path(String),
+    /// A package of the workspace, like `workspace:*`
+    Workspace(String),
+    /// A local directory or tarball, copied into node_modules
+    File(String),
+    /// A local directory, symlinked into node_modules
+    Link(String),
+    /// Another package installed under the name of the dependency, like `npm:other-package@^1.0.0`
+    Alias {
+        name: String,
+        version: Option<String>,
+    },
+    /// A repository with a hosted git shorthand, like `github:owner/repo#main&path:ui`
+    HostedGit {
+        host: GitHost,
+        owner: String,
+        repo: String,
+        committish: Option<String>,
+        /// Subdirectory of the repository with the package, as supported by pnpm
+        path: Option<String>,
+    },
+    /// A repository with a git url, like `git+https://host.com/owner/repo.git#main`
+    Git {
+        url: String,
+        committish: Option<String>,
+        /// Subdirectory of the repository with the package, as supported by pnpm
+        path: Option<String>,
+    },
+    /// A tarball url, like `https://host.com/package.tgz`
+    Tarball(String),
+    /// Any other source, like the GitHub shorthand `owner/repo` or the `catalog:`, `jsr:`, `portal:`
+    /// and `patch:` protocols of some package managers, kept as is
+    Other(String),
+}
+
+impl NpmDependencySpec {
+    pub fn parse(spec: &String) -> Result<NpmDependencySpec, NpmScaffoldingUtilsError> {
+        let invalid = |reason: &str| {
+            NpmScaffoldingUtilsError::InvalidNpmDependencySpecError(
+                spec.clone(),
+                reason.to_string(),
+            )
+        };
+
+        if let Some(range) = spec.strip_prefix("workspace:") {
+            return Ok(NpmDependencySpec::Workspace(range.to_string()));
+        }
+        if let Some(path) = spec.strip_prefix("file:") {
+            return Ok(NpmDependencySpec::File(path.to_string()));
+        }
+        if let Some(path) = spec.strip_prefix("link:") {
+            return Ok(NpmDependencySpec::Link(path.to_string()));
+        }
+        if let Some(alias) = spec.strip_prefix("npm:") {
+            // Skip the first character so that the "@" of a scoped package is not taken as the version separator
+            let (name, version) = match alias.char_indices().skip(1).find(|(_, c)| *c == '@') {
+                Some((i, _)) => (&alias[..i], Some(alias[i + 1..].to_string())),
+                None => (alias, None),
+            };
+            if name.is_empty() {
+                return Err(invalid("the aliased package has no name"));
+            }
+            return Ok(NpmDependencySpec::Alias {
+                name: name.to_string(),
+                version,
+            });
+        }
+
+        for host in [GitHost::GitHub, GitHost::GitLab, GitHost::Bitbucket] {
+            let Some(repository) = spec
+                .strip_prefix(host.prefix())
+                .and_then(|rest| rest.strip_prefix(':'))
+            else {
+                continue;
+            };
+            let (repository, fragment) = split_fragment(repository);
+            let Some((owner, repo)) = repository.split_once('/').filter(|(owner, repo)| {
+                !owner.is_empty() && !repo.is_empty() && !repo.contains('/')
+            }) else {
+                return Err(invalid("expected a repository like owner/repo"));
+            };
+            let (committish, path) = parse_git_fragment(fragment).map_err(invalid)?;
+
+            return Ok(NpmDependencySpec::HostedGit {
+                host,
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                committish,
+                path,
+            });
+        }
+
+        if spec.starts_with("git+") || spec.starts_with("git://") {
+            let (url, fragment) = split_fragment(spec);
+            let (committish, path) = parse_git_fragment(fragment).map_err(invalid)?;
+            return Ok(NpmDependencySpec::Git {
+                url: url.to_string(),
+                committish,
+                path,
+            });
+        }
+
+        if spec.starts_with("http://") || spec.starts_with("https://") {
+            return Ok(NpmDependencySpec::Tarball(spec.clone()));
+        }
+
+        if ["./", "../", "/", "~/"]
+            .iter()
+            .any(|prefix| spec.starts_with(prefix))
+        {
+            return Ok(NpmDependencySpec::Path(spec.clone()));
+        }
+
+        // Semver ranges, like ">=1.0.0 <2.0.0 || ^3.0.0", and dist-tags, like "latest" or "next_v2"
+        let is_version = spec
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " .-_+^~<>=*|".contains(c));
+        if is_version {
+            return Ok(NpmDependencySpec::Version(spec.clone()));
+        }
+
+        Ok(NpmDependencySpec::Other(spec.clone()))
+    }
+
+    /// The branch, tag or commit that the git repository of the dependency points to
+    pub fn committish(&self) -> Option<&String> {
+        match self {
+            NpmDependencySpec::HostedGit { committish, .. }
+            | NpmDependencySpec::Git { committish, .. } => committish.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Points the git repository of the dependency to the given branch, tag or commit
+    ///
+    /// Does nothing for dependencies that don't come from a git repository
+    pub fn set_committish(&mut self, new_committish: String) {
+        match self {
+            NpmDependencySpec::HostedGit { committish, .. }
+            | NpmDependencySpec::Git { committish, .. } => *committish = Some(new_committish),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for NpmDependencySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpmDependencySpec::Version(range) => write!(f, "{range}"),
+            NpmDependencySpec::Path(path) => write!(f, "{path}"),
+            NpmDependencySpec::Workspace(range) => write!(f, "workspace:{range}"),
+            NpmDependencySpec::File(path) => write!(f, "file:{path}"),
+            NpmDependencySpec::Link(path) => write!(f, "link:{path}"),
+            NpmDependencySpec::Alias { name, version } => match version {
+                Some(version) => write!(f, "npm:{name}@{version}"),
+                None => write!(f, "npm:{name}"),
+            },
+            NpmDependencySpec::HostedGit {
+                host,
+                owner,
+                repo,
+                committish,
+                path,
+            } => {
+                write!(f, "{}:{owner}/{repo}", host.prefix())?;
+                write_git_fragment(f, committish, path)
+            }
+            NpmDependencySpec::Git {
+                url,
+                committish,
+                path,
+            } => {
+                write!(f, "{url}")?;
+                write_git_fragment(f, committish, path)
+            }
+            NpmDependencySpec::Tarball(url) => write!(f, "{url}"),
+            NpmDependencySpec::Other(spec) => write!(f, "{spec}"),
+        }
+    }
+}
+
+fn split_fragment(spec: &str) -> (&str, Option<&str>) {
+    match spec.split_once('#') {
+        Some((before, fragment)) => (before, Some(fragment)),
+        None => (spec, None),
+    }
+}
+
+/// Parses the `#committish&path:subdir` fragment of a git dependency into its committish and its path
+fn parse_git_fragment(
+    fragment: Option<&str>,
+) -> Result<(Option<String>, Option<String>), &'static str> {
+    let mut committish = None;
+    let mut path = None;
+
+    for argument in fragment.unwrap_or_default().split('&') {
+        if argument.is_empty() {
+            continue;
+        }
+        match argument.strip_prefix("path:") {
+            Some(subdir) => path = Some(subdir.to_string()),
+            None if committish.is_none() => committish = Some(argument.to_string()),
+            None => return Err("the git fragment has more than one committish"),
+        }
+    }
+
+    Ok((committish, path))
+}
+
+fn write_git_fragment(
+    f: &mut fmt::Formatter<'_>,
+    committish: &Option<String>,
+    path: &Option<String>,
+) -> fmt::Result {
+    match (committish, path) {
+        (Some(committish), Some(path)) => write!(f, "#{committish}&path:{path}"),
+        (Some(committish), None) => write!(f, "#{committish}"),
+        (None, Some(path)) => write!(f, "#path:{path}"),
+        (None, None) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        for spec in [
+            "^0.300.0",
+            ">=1.0.0 <2.0.0 || 3.x",
+            "latest",
+            "workspace:*",
+            "file:../ui",
+            "link:../../module/ui",
+            "npm:@holochain-open-dev/elements@^0.300.0",
+            "github:holochain-open-dev/profiles#main&path:ui",
+            "gitlab:owner/repo#path:ui",
+            "git+https://host.com/owner/repo.git#v0.1.0",
+            "git+ssh://git@host.com/owner/repo.git",
+            "https://host.com/package.tgz",
+            "../ui",
+            "next_v2",
+            "owner/repo#main",
+            "catalog:",
+            "jsr:@std/path@^1.0.0",
+            "portal:../ui",
+            "patch:lit@npm%3A3.0.0#./patches/lit.patch",
+        ] {
+            let spec = String::from(spec);
+            assert_eq!(NpmDependencySpec::parse(&spec).unwrap().to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            NpmDependencySpec::parse(&String::from(
+                "github:holochain-open-dev/profiles#main&path:ui"
+            ))
+            .unwrap(),
+            NpmDependencySpec::HostedGit {
+                host: GitHost::GitHub,
+                owner: "holochain-open-dev".into(),
+                repo: "profiles".into(),
+                committish: Some("main".into()),
+                path: Some("ui".into()),
+            }
+        );
+        assert_eq!(
+            NpmDependencySpec::parse(&String::from("npm:@scope/package")).unwrap(),
+            NpmDependencySpec::Alias {
+                name: "@scope/package".into(),
+                version: None,
+            }
+        );
+        assert_eq!(
+            NpmDependencySpec::parse(&String::from("../ui")).unwrap(),
+            NpmDependencySpec::Path("../ui".into())
+        );
+        assert_eq!(
+            NpmDependencySpec::parse(&String::from("owner/repo")).unwrap(),
+            NpmDependencySpec::Other("owner/repo".into())
+        );
+        assert!(NpmDependencySpec::parse(&String::from("github:owner")).is_err());
+        assert!(NpmDependencySpec::parse(&String::from("npm:")).is_err());
+    }
+}
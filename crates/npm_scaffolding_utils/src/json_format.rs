@@ -0,0 +1,658 @@
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Map, Serializer, Value};
+
+use crate::NpmScaffoldingUtilsError;
+
+/// Serializes the given JSON value by editing the original text it was read from, so that only the values
+/// that changed are rewritten
+///
+/// The indentation style, the key order, the layout of untouched values and the trailing newline
+/// of the original text are kept, which avoids noisy diffs against files formatted with tools like Prettier
+pub fn to_string_preserving_format(
+    original: &String,
+    value: &Value,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let original_value: Value = serde_json::from_str(original)?;
+
+    let Some(root) = JsonScanner::new(original).parse_value() else {
+        return Ok(serde_json::to_string_pretty(value)?);
+    };
+
+    let indent_unit = match &root {
+        JsonNode::Object { members, .. } => members
+            .first()
+            .filter(|member| is_first_in_line(original, member.key_start))
+            .map(|member| line_indentation(original, member.key_start))
+            .filter(|indentation| !indentation.is_empty())
+            .unwrap_or("  "),
+        JsonNode::Array { .. } | JsonNode::Other { .. } => "  ",
+    };
+
+    let mut edits: Vec<Edit> = vec![];
+    diff_values(
+        original,
+        indent_unit,
+        &root,
+        &original_value,
+        value,
+        &mut edits,
+    )?;
+
+    // Apply the edits from the end of the text, so that the positions of the remaining ones stay valid
+    edits.sort_by(|a, b| b.start.cmp(&a.start).then(b.end.cmp(&a.end)));
+    let mut result = original.clone();
+    for edit in edits {
+        result.replace_range(edit.start..edit.end, &edit.text);
+    }
+
+    Ok(result)
+}
+
+/// Position of a parsed JSON value in its text
+enum JsonNode {
+    Object {
+        start: usize,
+        end: usize,
+        members: Vec<JsonMember>,
+    },
+    Array {
+        start: usize,
+        end: usize,
+        items: Vec<JsonNode>,
+    },
+    Other {
+        start: usize,
+        end: usize,
+    },
+}
+
+struct JsonMember {
+    key: String,
+    key_start: usize,
+    value: JsonNode,
+}
+
+impl JsonNode {
+    fn start(&self) -> usize {
+        match self {
+            JsonNode::Object { start, .. }
+            | JsonNode::Array { start, .. }
+            | JsonNode::Other { start, .. } => *start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        match self {
+            JsonNode::Object { end, .. }
+            | JsonNode::Array { end, .. }
+            | JsonNode::Other { end, .. } => *end,
+        }
+    }
+}
+
+/// Replaces the text between `start` and `end` with `text`
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Finds the positions of the values of a JSON text, only looking into objects and arrays
+struct JsonScanner<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(text: &'a str) -> Self {
+        JsonScanner {
+            text,
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<JsonNode> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        match self.bytes.get(self.pos)? {
+            b'{' => return self.parse_object(),
+            b'[' => return self.parse_array(),
+            b'"' => self.skip_string()?,
+            _ => {
+                while self
+                    .bytes
+                    .get(self.pos)
+                    .is_some_and(|b| !(b.is_ascii_whitespace() || b",]}".contains(b)))
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return None;
+                }
+            }
+        }
+
+        Some(JsonNode::Other {
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.pos += 1;
+        loop {
+            match self.bytes.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Option<JsonNode> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut items = vec![];
+
+        loop {
+            self.skip_whitespace();
+            match self.bytes.get(self.pos)? {
+                b']' => {
+                    self.pos += 1;
+                    return Some(JsonNode::Array {
+                        start,
+                        end: self.pos,
+                        items,
+                    });
+                }
+                b',' => self.pos += 1,
+                _ => items.push(self.parse_value()?),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Option<JsonNode> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut members = vec![];
+
+        loop {
+            self.skip_whitespace();
+            match self.bytes.get(self.pos)? {
+                b'}' => {
+                    self.pos += 1;
+                    return Some(JsonNode::Object {
+                        start,
+                        end: self.pos,
+                        members,
+                    });
+                }
+                b',' => self.pos += 1,
+                b'"' => {
+                    let key_start = self.pos;
+                    self.skip_string()?;
+                    let key: String = serde_json::from_str(&self.text[key_start..self.pos]).ok()?;
+
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos)? != &b':' {
+                        return None;
+                    }
+                    self.pos += 1;
+
+                    let value = self.parse_value()?;
+                    members.push(JsonMember {
+                        key,
+                        key_start,
+                        value,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+fn diff_values(
+    text: &String,
+    indent_unit: &str,
+    node: &JsonNode,
+    original_value: &Value,
+    value: &Value,
+    edits: &mut Vec<Edit>,
+) -> Result<(), NpmScaffoldingUtilsError> {
+    if original_value.eq(value) {
+        return Ok(());
+    }
+
+    match (node, original_value, value) {
+        (
+            JsonNode::Object {
+                start,
+                end,
+                members,
+            },
+            Value::Object(original_map),
+            Value::Object(map),
+        ) => diff_objects(
+            text,
+            indent_unit,
+            (*start, *end),
+            members,
+            original_map,
+            map,
+            edits,
+        ),
+        (
+            JsonNode::Array { start, end, items },
+            Value::Array(original_items),
+            Value::Array(new_items),
+        ) => diff_arrays(
+            text,
+            indent_unit,
+            (*start, *end),
+            items,
+            original_items,
+            new_items,
+            edits,
+        ),
+        _ => {
+            edits.push(Edit {
+                start: node.start(),
+                end: node.end(),
+                text: to_string_indented(value, indent_unit, line_indentation(text, node.start()))?,
+            });
+            Ok(())
+        }
+    }
+}
+
+fn diff_objects(
+    text: &String,
+    indent_unit: &str,
+    (start, end): (usize, usize),
+    members: &Vec<JsonMember>,
+    original_map: &Map<String, Value>,
+    map: &Map<String, Value>,
+    edits: &mut Vec<Edit>,
+) -> Result<(), NpmScaffoldingUtilsError> {
+    let layout = EntriesLayout::new(
+        text,
+        indent_unit,
+        (start, end),
+        members.first().map(|member| member.key_start),
+    );
+
+    let added_members = map
+        .iter()
+        .filter(|(key, _)| !original_map.contains_key(*key))
+        .map(|(key, value)| -> Result<String, NpmScaffoldingUtilsError> {
+            Ok(format!(
+                "{}: {}",
+                serde_json::to_string(key)?,
+                layout.format_value(value, indent_unit)?
+            ))
+        })
+        .collect::<Result<Vec<String>, NpmScaffoldingUtilsError>>()?;
+
+    let mut kept = vec![];
+    for member in members {
+        match map.get(&member.key) {
+            Some(value) => {
+                diff_values(
+                    text,
+                    indent_unit,
+                    &member.value,
+                    &original_map[&member.key],
+                    value,
+                    edits,
+                )?;
+                kept.push(true);
+            }
+            None => kept.push(false),
+        }
+    }
+
+    let spans: Vec<(usize, usize)> = members
+        .iter()
+        .map(|member| (member.key_start, member.value.end()))
+        .collect();
+    edit_entries(
+        text,
+        (start, end),
+        &layout,
+        &spans,
+        &kept,
+        added_members,
+        edits,
+    );
+
+    Ok(())
+}
+
+fn diff_arrays(
+    text: &String,
+    indent_unit: &str,
+    (start, end): (usize, usize),
+    items: &[JsonNode],
+    original_items: &[Value],
+    new_items: &[Value],
+    edits: &mut Vec<Edit>,
+) -> Result<(), NpmScaffoldingUtilsError> {
+    // Items changed in place are diffed one by one, to keep the layout of the nested values
+    if original_items.len() == new_items.len() {
+        for ((item, original_item), new_item) in items.iter().zip(original_items).zip(new_items) {
+            diff_values(text, indent_unit, item, original_item, new_item, edits)?;
+        }
+        return Ok(());
+    }
+
+    // Otherwise, the original items are kept while they match the new ones in order,
+    // and the new items that are left are appended after them
+    let mut next_new_item = 0;
+    let mut kept = vec![];
+    for original_item in original_items {
+        let is_kept = new_items
+            .get(next_new_item)
+            .is_some_and(|new_item| new_item.eq(original_item));
+        if is_kept {
+            next_new_item += 1;
+        }
+        kept.push(is_kept);
+    }
+
+    let layout = EntriesLayout::new(
+        text,
+        indent_unit,
+        (start, end),
+        items.first().map(|item| item.start()),
+    );
+    let added_items = new_items[next_new_item..]
+        .iter()
+        .map(|item| layout.format_value(item, indent_unit))
+        .collect::<Result<Vec<String>, NpmScaffoldingUtilsError>>()?;
+
+    let spans: Vec<(usize, usize)> = items
+        .iter()
+        .map(|item| (item.start(), item.end()))
+        .collect();
+    edit_entries(
+        text,
+        (start, end),
+        &layout,
+        &spans,
+        &kept,
+        added_items,
+        edits,
+    );
+
+    Ok(())
+}
+
+/// How the members of an object or the items of an array are laid out in the original text
+struct EntriesLayout {
+    single_line: bool,
+    indentation: String,
+}
+
+impl EntriesLayout {
+    fn new(
+        text: &str,
+        indent_unit: &str,
+        (start, end): (usize, usize),
+        first_entry_start: Option<usize>,
+    ) -> Self {
+        // Empty objects and arrays are expanded when entries are added to them
+        let single_line = first_entry_start.is_some() && !text[start..end].contains('\n');
+        let indentation = match first_entry_start {
+            Some(entry_start) if is_first_in_line(text, entry_start) => {
+                line_indentation(text, entry_start).to_string()
+            }
+            _ => format!("{}{indent_unit}", line_indentation(text, start)),
+        };
+
+        EntriesLayout {
+            single_line,
+            indentation,
+        }
+    }
+
+    fn format_value(
+        &self,
+        value: &Value,
+        indent_unit: &str,
+    ) -> Result<String, NpmScaffoldingUtilsError> {
+        match self.single_line {
+            true => Ok(serde_json::to_string(value)?),
+            false => to_string_indented(value, indent_unit, &self.indentation),
+        }
+    }
+
+    fn separator(&self) -> String {
+        match self.single_line {
+            true => String::from(", "),
+            false => format!(",\n{}", self.indentation),
+        }
+    }
+}
+
+/// Removes the entries of an object or an array that are not kept, together with their commas,
+/// and appends the added ones after the last kept entry
+///
+/// `spans` are the start and end positions of the original entries
+fn edit_entries(
+    text: &str,
+    (start, end): (usize, usize),
+    layout: &EntriesLayout,
+    spans: &[(usize, usize)],
+    kept: &[bool],
+    added: Vec<String>,
+    edits: &mut Vec<Edit>,
+) {
+    let Some(first_kept) = kept.iter().position(|is_kept| *is_kept) else {
+        // All the entries were removed: rewrite the whole content
+        let content = match (added.is_empty(), layout.single_line) {
+            (true, _) => String::new(),
+            (false, true) => format!(" {} ", added.join(", ")),
+            (false, false) => format!(
+                "\n{}{}\n{}",
+                layout.indentation,
+                added.join(&layout.separator()),
+                line_indentation(text, start)
+            ),
+        };
+        edits.push(Edit {
+            start: start + 1,
+            end: end - 1,
+            text: content,
+        });
+        return;
+    };
+
+    // The entries removed before the first kept one are removed up to it, together with their commas
+    if first_kept > 0 {
+        edits.push(Edit {
+            start: spans[0].0,
+            end: spans[first_kept].0,
+            text: String::new(),
+        });
+    }
+
+    let mut last_kept = first_kept;
+    for (i, is_kept) in kept.iter().enumerate().skip(first_kept) {
+        match *is_kept {
+            true => last_kept = i,
+            false => edits.push(Edit {
+                start: spans[i - 1].1,
+                end: spans[i].1,
+                text: String::new(),
+            }),
+        }
+    }
+
+    if !added.is_empty() {
+        let separator = layout.separator();
+        edits.push(Edit {
+            start: spans[last_kept].1,
+            end: spans[last_kept].1,
+            text: format!("{separator}{}", added.join(&separator)),
+        });
+    }
+}
+
+/// Serializes the value with the given indentation unit, for it to be placed in a line with the given indentation
+fn to_string_indented(
+    value: &Value,
+    indent_unit: &str,
+    line_indentation: &str,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let mut writer = Vec::new();
+    let mut serializer = Serializer::with_formatter(
+        &mut writer,
+        PrettyFormatter::with_indent(indent_unit.as_bytes()),
+    );
+    value.serialize(&mut serializer)?;
+
+    // Newlines inside JSON strings are escaped, so all of these are line breaks of the serialized value
+    Ok(String::from_utf8_lossy(&writer).replace('\n', &format!("\n{line_indentation}")))
+}
+
+fn line_start(text: &str, position: usize) -> usize {
+    text[..position].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+fn line_indentation(text: &str, position: usize) -> &str {
+    let line = &text[line_start(text, position)..position];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn is_first_in_line(text: &str, position: usize) -> bool {
+    text[line_start(text, position)..position].trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserve_format_test() {
+        let original = String::from(
+            r#"{
+    "name": "ui",
+    "files": ["dist"],
+    "scripts": {
+        "build": "tsc"
+    },
+    "dependencies": {},
+    "devDependencies": {
+        "typescript": "^5.4.0",
+        "vite": "^5.0.0"
+    },
+    "prettier": { "singleQuote": true }
+}
+"#,
+        );
+
+        let mut value: Value = serde_json::from_str(&original).unwrap();
+        value["dependencies"]["@holochain-open-dev/profiles"] = Value::from("^0.300.0");
+        value["devDependencies"]
+            .as_object_mut()
+            .unwrap()
+            .shift_remove("typescript");
+        value["prettier"]["semi"] = Value::from(false);
+        value["peerDependencies"] = serde_json::json!({ "lit": "^3.0.0" });
+
+        assert_eq!(
+            to_string_preserving_format(&original, &value).unwrap(),
+            r#"{
+    "name": "ui",
+    "files": ["dist"],
+    "scripts": {
+        "build": "tsc"
+    },
+    "dependencies": {
+        "@holochain-open-dev/profiles": "^0.300.0"
+    },
+    "devDependencies": {
+        "vite": "^5.0.0"
+    },
+    "prettier": { "singleQuote": true, "semi": false },
+    "peerDependencies": {
+        "lit": "^3.0.0"
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn preserve_tabs_test() {
+        let original = String::from("{\n\t\"name\": \"ui\",\n\t\"dependencies\": {\n\t\t\"a\": \"1\",\n\t\t\"b\": \"2\"\n\t}\n}");
+
+        let mut value: Value = serde_json::from_str(&original).unwrap();
+        value["dependencies"]
+            .as_object_mut()
+            .unwrap()
+            .shift_remove("b");
+        value["dependencies"]["c"] = Value::from("3");
+
+        assert_eq!(
+            to_string_preserving_format(&original, &value).unwrap(),
+            "{\n\t\"name\": \"ui\",\n\t\"dependencies\": {\n\t\t\"a\": \"1\",\n\t\t\"c\": \"3\"\n\t}\n}"
+        );
+    }
+
+    #[test]
+    fn preserve_arrays_format_test() {
+        let original = String::from(
+            r#"{
+  "name": "root",
+  "workspaces": ["ui"],
+  "files": [
+    "dist",
+    "src"
+  ],
+  "keywords": []
+}
+"#,
+        );
+
+        let mut value: Value = serde_json::from_str(&original).unwrap();
+        value["workspaces"]
+            .as_array_mut()
+            .unwrap()
+            .push(Value::from("modules/profiles"));
+        value["files"].as_array_mut().unwrap().remove(0);
+        value["keywords"] = serde_json::json!(["holochain"]);
+
+        assert_eq!(
+            to_string_preserving_format(&original, &value).unwrap(),
+            r#"{
+  "name": "root",
+  "workspaces": ["ui", "modules/profiles"],
+  "files": [
+    "src"
+  ],
+  "keywords": [
+    "holochain"
+  ]
+}
+"#
+        );
+    }
+}
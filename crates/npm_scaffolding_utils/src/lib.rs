@@ -1,18 +1,32 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use dialoguer::{theme::ColorfulTheme, Select};
-use file_tree_utils::{find_files_by_name, map_file, FileTree, FileTreeError, file_exists, file_content};
+use file_tree_utils::{map_file, FileTree, FileTreeError, file_exists, file_content};
 use serde::{Serialize, Deserialize};
+use prompt_utils::{Chooser, PromptError};
 use serde_json::{Map, Value};
 use thiserror::Error;
 
+mod dependency_spec;
+mod json_format;
+mod npm_package;
+mod npm_workspace;
+pub use dependency_spec::{GitHost, NpmDependencySpec};
+pub use json_format::to_string_preserving_format;
+pub use npm_package::{scaffold_npm_package, NpmPackageTemplate};
+pub use npm_workspace::{
+    add_npm_workspace_member, find_npm_workspace, NpmWorkspace, NpmWorkspaceKind,
+};
+
 #[derive(Error, Debug)]
 pub enum NpmScaffoldingUtilsError {
     #[error(transparent)]
     FileTreeError(#[from] FileTreeError),
 
     #[error(transparent)]
-    DialoguerError(#[from] dialoguer::Error),
+    PromptError(#[from] PromptError),
 
     #[error("No NPM packages were found in this file tree")]
     NoNpmPackagesFoundError,
@@ -25,20 +39,56 @@ pub enum NpmScaffoldingUtilsError {
 
     #[error("NPM package not found: {0}")]
     NpmPackageNotFoundError(String),
+
+    #[error("No NPM package depends on {0}")]
+    NpmDependencyNotFoundError(String),
+
+    #[error("NPM package already exists: {0}")]
+    NpmPackageAlreadyExistsError(PathBuf),
+
+    #[error("YAML serialization error: {0}")]
+    SerdeYamlError(#[from] serde_yaml::Error),
+
+    #[error("Invalid workspace pattern {0}: {1}")]
+    InvalidWorkspacePatternError(String, String),
+
+    #[error("The packages of pnpm-workspace.yaml are not a block list, add {0} to them by hand")]
+    PnpmWorkspaceNotEditableError(String),
+
+    #[error("Invalid NPM dependency source {0}: {1}")]
+    InvalidNpmDependencySpecError(String, String),
+
+    #[error("Failed to {1} in {0}")]
+    PackageJsonNotUpdatedError(PathBuf, String),
+
+    #[error("{0} can't run \"{1}\" for a package of the workspace, run it in the directory of the package instead")]
+    UnsupportedWorkspaceCommandError(String, String),
 }
 
-fn default_select_npm_package(
-    npm_packages: Vec<String>,
+/// Chooses the package to add the given dependency to, defaulting to the first one that's not a dev or test package
+fn choose_npm_package_for_dependency(
+    dependency: &String,
+    npm_packages: &Vec<String>,
+    chooser: &dyn Chooser,
 ) -> Result<usize, NpmScaffoldingUtilsError> {
-    Ok(Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Multiple NPM packages were found in this repository, choose one:")
-        .default(0)
-        .items(&npm_packages[..])
-        .interact()?)
+    let default = npm_packages
+        .iter()
+        .position(|package| !(package.ends_with("-dev") || package.contains("test")))
+        .unwrap_or(0);
+
+    Ok(chooser.choose(
+        &format!("Multiple NPM packages were found in this repository, choose one to add the {dependency} dependency to:"),
+        npm_packages,
+        default,
+    )?)
 }
 
-pub fn choose_npm_package(file_tree: &FileTree, prompt: &String) -> Result<String, NpmScaffoldingUtilsError> {
-    let package_jsons = find_files_by_name(&file_tree, PathBuf::from("package.json").as_path());
+pub fn choose_npm_package(
+    file_tree: &FileTree,
+    prompt: &String,
+    chooser: &dyn Chooser,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let package_jsons = find_npm_workspace(file_tree)?.candidate_packages();
 
     let package_jsons: Vec<(PathBuf, String)> = package_jsons.into_iter().collect();
     let packages_names = package_jsons
@@ -46,39 +96,24 @@ pub fn choose_npm_package(file_tree: &FileTree, prompt: &String) -> Result<Strin
         .map(|package_json| get_npm_package_name(package_json))
         .collect::<Result<Vec<String>, NpmScaffoldingUtilsError>>()?;
     
-    let index = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(prompt)
-        .default(0)
-        .items(&packages_names[..])
-        .interact()?;
+    let index = chooser.choose(prompt, &packages_names, 0)?;
 
     Ok(packages_names[index].clone())
 }
 
-pub fn add_npm_dependency(
-    mut file_tree: FileTree,
-    dependency: String,
-    dependency_source: String,
+/// Finds the package.json of the package to add the given dependency to: the only package in the repository,
+/// the one with the given name, or the one chosen among all of them
+pub fn get_or_choose_package_json_for_dependency(
+    file_tree: &FileTree,
+    dependency: &String,
     package_to_add_the_dependency_to: Option<String>,
-    custom_select_npm_package: Option<
-        Box<dyn Fn(Vec<String>) -> Result<usize, NpmScaffoldingUtilsError>>,
-    >,
-) -> Result<FileTree, NpmScaffoldingUtilsError> {
-    let mut package_jsons = find_files_by_name(&file_tree, PathBuf::from("package.json").as_path());
+    chooser: &dyn Chooser,
+) -> Result<(PathBuf, String), NpmScaffoldingUtilsError> {
+    let mut package_jsons = find_npm_workspace(file_tree)?.candidate_packages();
 
     match package_jsons.len() {
-        0 => Err(NpmScaffoldingUtilsError::NoNpmPackagesFoundError)?,
-        1 => {
-            let package_json = package_jsons.pop_first().unwrap();
-            map_file(
-                &mut file_tree,
-                package_json.0.as_path(),
-                |_package_json_content| {
-                    add_npm_dependency_to_package(&package_json, &dependency, &dependency_source)
-                },
-            )?;
-            println!("Added dependency {dependency} to {:?}.", package_json.0);
-        }
+        0 => Err(NpmScaffoldingUtilsError::NoNpmPackagesFoundError),
+        1 => Ok(package_jsons.pop_first().unwrap()),
         _ => {
             let package_jsons: Vec<(PathBuf, String)> = package_jsons.into_iter().collect();
             let packages_names = package_jsons
@@ -93,24 +128,48 @@ pub fn add_npm_dependency(
                     .ok_or(NpmScaffoldingUtilsError::NpmPackageNotFoundError(
                         package_to_add_to.clone(),
                     ))?,
-                None => match custom_select_npm_package {
-                    Some(selector) => selector(packages_names)?,
-                    None => default_select_npm_package(packages_names)?,
-                },
+                None => choose_npm_package_for_dependency(dependency, &packages_names, chooser)?,
             };
 
-            let package_json = &package_jsons[package_index];
-
-            map_file(
-                &mut file_tree,
-                package_json.0.as_path(),
-                |_package_json_content| {
-                    add_npm_dependency_to_package(package_json, &dependency, &dependency_source)
-                },
-            )?;
-            println!("Added dependency {dependency} to {:?}.", package_json.0);
+            Ok(package_jsons[package_index].clone())
         }
-    };
+    }
+}
+
+pub fn add_npm_dependency(
+    mut file_tree: FileTree,
+    dependency: String,
+    dependency_source: String,
+    dependency_kind: DependencyKind,
+    package_to_add_the_dependency_to: Option<String>,
+    chooser: &dyn Chooser,
+) -> Result<FileTree, NpmScaffoldingUtilsError> {
+    NpmDependencySpec::parse(&dependency_source)?;
+
+    let package_json = get_or_choose_package_json_for_dependency(
+        &file_tree,
+        &dependency,
+        package_to_add_the_dependency_to,
+        chooser,
+    )?;
+
+    map_file(
+        &mut file_tree,
+        package_json.0.as_path(),
+        |_package_json_content| {
+            add_npm_dependency_of_kind_to_package(
+                &package_json,
+                &dependency,
+                &dependency_source,
+                dependency_kind,
+            )
+        },
+    )?;
+    eprintln!(
+        "Added dependency {dependency} to the {} of {:?}.",
+        dependency_kind.package_json_section(),
+        package_json.0
+    );
 
     Ok(file_tree)
 }
@@ -143,11 +202,37 @@ fn get_npm_package_name(
     Ok(name.clone())
 }
 
-pub fn add_npm_dependency_to_package(
+/// Section of a package.json in which a dependency is declared
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DependencyKind {
+    #[default]
+    Regular,
+    Dev,
+    Peer,
+    Optional,
+}
+
+impl DependencyKind {
+    /// Name of the package.json property that lists the dependencies of this kind
+    pub fn package_json_section(&self) -> &'static str {
+        match self {
+            DependencyKind::Regular => "dependencies",
+            DependencyKind::Dev => "devDependencies",
+            DependencyKind::Peer => "peerDependencies",
+            DependencyKind::Optional => "optionalDependencies",
+        }
+    }
+}
+
+/// Adds the given dependency to the section of the package.json for its kind, creating the section if needed
+pub fn add_npm_dependency_of_kind_to_package(
     package_json: &(PathBuf, String),
     dependency: &String,
     dependency_source: &String,
+    dependency_kind: DependencyKind,
 ) -> Result<String, NpmScaffoldingUtilsError> {
+    let section = dependency_kind.package_json_section();
+
     let mut json: serde_json::Value = serde_json::from_str(package_json.1.as_str())?;
 
     let Some(map) = json.as_object_mut() else {
@@ -157,14 +242,17 @@ pub fn add_npm_dependency_to_package(
         ));
     };
 
-    let mut stub = serde_json::Value::Object(Map::new());
+    if !map.contains_key(section) {
+        let stub = serde_json::Value::Object(Map::new());
+        map.insert(section.into(), stub);
+    }
 
-    let dependencies_value = map.get_mut("dependencies").unwrap_or(&mut stub);
+    let dependencies_value = map.get_mut(section).unwrap();
 
     let Some(dependencies) = dependencies_value.as_object_mut() else {
         return Err(NpmScaffoldingUtilsError::MalformedJsonError(
             package_json.0.to_path_buf(),
-            String::from(r#"the "dependencies" property is not a json object"#),
+            format!(r#"the "{section}" property is not a json object"#),
         ));
     };
 
@@ -173,13 +261,62 @@ pub fn add_npm_dependency_to_package(
         serde_json::Value::String(dependency_source.clone()),
     );
 
-    Ok(serde_json::to_string_pretty(&json)?)
+    check_package_json_change(
+        package_json,
+        to_string_preserving_format(&package_json.1, &json)?,
+        format!(r#"add dependency {dependency} to "{section}""#),
+        |json| {
+            json.get(section)
+                .and_then(|dependencies| dependencies.get(dependency))
+                .is_some_and(|source| source.eq(dependency_source))
+        },
+    )
 }
 
-pub fn add_npm_dev_dependency_to_package(
+/// Verifies that the new content of the package.json contains the requested change, so that a change
+/// that got lost along the way results in an error instead of a silently untouched file
+fn check_package_json_change<F: Fn(&Value) -> bool>(
     package_json: &(PathBuf, String),
-    dev_dependency: &String,
-    dev_dependency_source: &String,
+    new_content: String,
+    change_description: String,
+    is_change_applied: F,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let json: serde_json::Value = serde_json::from_str(new_content.as_str())?;
+
+    match is_change_applied(&json) {
+        true => Ok(new_content),
+        false => Err(NpmScaffoldingUtilsError::PackageJsonNotUpdatedError(
+            package_json.0.clone(),
+            change_description,
+        )),
+    }
+}
+
+pub fn add_npm_dependency_to_package(
+    package_json: &(PathBuf, String),
+    dependency: &String,
+    dependency_source: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    add_npm_dependency_of_kind_to_package(
+        package_json,
+        dependency,
+        dependency_source,
+        DependencyKind::Regular,
+    )
+}
+
+/// Sections of a package.json in which its dependencies are declared
+pub const NPM_DEPENDENCY_SECTIONS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Applies the given edit to each of the dependency sections that the package.json has
+fn edit_npm_dependency_sections<F: Fn(&mut Map<String, Value>)>(
+    package_json: &(PathBuf, String),
+    edit: F,
 ) -> Result<String, NpmScaffoldingUtilsError> {
     let mut json: serde_json::Value = serde_json::from_str(package_json.1.as_str())?;
 
@@ -190,26 +327,183 @@ pub fn add_npm_dev_dependency_to_package(
         ));
     };
 
-    if !map.contains_key("devDependencies") {
-        let stub = serde_json::Value::Object(Map::new());
-        map.insert("devDependencies".into(), stub);
+    for section in NPM_DEPENDENCY_SECTIONS {
+        let Some(dependencies_value) = map.get_mut(section) else {
+            continue;
+        };
+        let Some(dependencies) = dependencies_value.as_object_mut() else {
+            return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                package_json.0.to_path_buf(),
+                format!(r#"the "{section}" property is not a json object"#),
+            ));
+        };
+
+        edit(dependencies);
     }
 
-    let dependencies_value = map.get_mut("devDependencies").unwrap();
+    to_string_preserving_format(&package_json.1, &json)
+}
 
-    let Some(dependencies) = dependencies_value.as_object_mut() else {
-        return Err(NpmScaffoldingUtilsError::MalformedJsonError(
-            package_json.0.to_path_buf(),
-            String::from(r#"the "devDependencies" property is not a json object"#),
+/// Removes the given dependency from all the dependency sections of the package.json
+pub fn remove_npm_dependency_from_package(
+    package_json: &(PathBuf, String),
+    dependency: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let new_content = edit_npm_dependency_sections(package_json, |dependencies| {
+        dependencies.shift_remove(dependency);
+    })?;
+
+    check_package_json_change(
+        package_json,
+        new_content,
+        format!("remove dependency {dependency}"),
+        |json| {
+            NPM_DEPENDENCY_SECTIONS
+                .iter()
+                .all(|section| json.get(section).and_then(|d| d.get(dependency)).is_none())
+        },
+    )
+}
+
+/// Sets the source of the given dependency in the dependency sections of the package.json that declare it
+pub fn update_npm_dependency_source_in_package(
+    package_json: &(PathBuf, String),
+    dependency: &String,
+    dependency_source: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let new_content = edit_npm_dependency_sections(package_json, |dependencies| {
+        if let Some(source) = dependencies.get_mut(dependency) {
+            *source = Value::String(dependency_source.clone());
+        }
+    })?;
+
+    check_package_json_change(
+        package_json,
+        new_content,
+        format!("set dependency {dependency} to {dependency_source}"),
+        |json| {
+            !NPM_DEPENDENCY_SECTIONS.iter().any(|section| {
+                json.get(section)
+                    .and_then(|d| d.get(dependency))
+                    .is_some_and(|source| source.ne(dependency_source))
+            })
+        },
+    )
+}
+
+/// Finds the packages of the repository that declare the given dependency in any of their dependency sections
+pub fn find_packages_with_npm_dependency(
+    file_tree: &FileTree,
+    dependency: &String,
+) -> Result<BTreeMap<PathBuf, String>, NpmScaffoldingUtilsError> {
+    let mut packages = BTreeMap::new();
+
+    for (package_json_path, package_json_content) in find_npm_workspace(file_tree)?.all_packages() {
+        let json: serde_json::Value = serde_json::from_str(package_json_content.as_str())?;
+
+        let declares_dependency = NPM_DEPENDENCY_SECTIONS.iter().any(|section| {
+            json.get(section)
+                .and_then(|dependencies| dependencies.get(dependency))
+                .is_some()
+        });
+        if declares_dependency {
+            packages.insert(package_json_path, package_json_content);
+        }
+    }
+
+    Ok(packages)
+}
+
+/// Packages that declare the given dependency, restricted to the package with the given name if there is one
+fn packages_to_change_dependency_in(
+    file_tree: &FileTree,
+    dependency: &String,
+    package_name: &Option<String>,
+) -> Result<Vec<(PathBuf, String)>, NpmScaffoldingUtilsError> {
+    let mut package_jsons = vec![];
+    for package_json in find_packages_with_npm_dependency(file_tree, dependency)? {
+        if let Some(package_name) = package_name {
+            if get_npm_package_name(&package_json)?.ne(package_name) {
+                continue;
+            }
+        }
+        package_jsons.push(package_json);
+    }
+
+    if package_jsons.is_empty() {
+        return Err(NpmScaffoldingUtilsError::NpmDependencyNotFoundError(
+            dependency.clone(),
         ));
-    };
+    }
 
-    dependencies.insert(
-        dev_dependency.clone(),
-        serde_json::Value::String(dev_dependency_source.clone()),
-    );
+    Ok(package_jsons)
+}
+
+/// Removes the given dependency from all the packages that declare it, or only from the package with the given name
+pub fn remove_npm_dependency(
+    mut file_tree: FileTree,
+    dependency: String,
+    package_to_remove_the_dependency_from: Option<String>,
+) -> Result<FileTree, NpmScaffoldingUtilsError> {
+    let package_jsons = packages_to_change_dependency_in(
+        &file_tree,
+        &dependency,
+        &package_to_remove_the_dependency_from,
+    )?;
+
+    for (package_json_path, _) in package_jsons {
+        map_file(&mut file_tree, &package_json_path, |package_json_content| {
+            remove_npm_dependency_from_package(
+                &(package_json_path.clone(), package_json_content),
+                &dependency,
+            )
+        })?;
+        eprintln!("Removed dependency {dependency} from {package_json_path:?}.");
+    }
+
+    Ok(file_tree)
+}
+
+/// Sets the source of the given dependency in all the packages that declare it, or only in the package with the given name
+pub fn update_npm_dependency_source(
+    mut file_tree: FileTree,
+    dependency: String,
+    dependency_source: String,
+    package_to_update_the_dependency_in: Option<String>,
+) -> Result<FileTree, NpmScaffoldingUtilsError> {
+    NpmDependencySpec::parse(&dependency_source)?;
+
+    let package_jsons = packages_to_change_dependency_in(
+        &file_tree,
+        &dependency,
+        &package_to_update_the_dependency_in,
+    )?;
+
+    for (package_json_path, _) in package_jsons {
+        map_file(&mut file_tree, &package_json_path, |package_json_content| {
+            update_npm_dependency_source_in_package(
+                &(package_json_path.clone(), package_json_content),
+                &dependency,
+                &dependency_source,
+            )
+        })?;
+        eprintln!("Set dependency {dependency} to {dependency_source} in {package_json_path:?}.");
+    }
 
-    Ok(serde_json::to_string_pretty(&json)?)
+    Ok(file_tree)
+}
+
+pub fn add_npm_dev_dependency_to_package(
+    package_json: &(PathBuf, String),
+    dev_dependency: &String,
+    dev_dependency_source: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    add_npm_dependency_of_kind_to_package(
+        package_json,
+        dev_dependency,
+        dev_dependency_source,
+        DependencyKind::Dev,
+    )
 }
 
 pub fn add_npm_script_to_package(
@@ -245,7 +539,16 @@ pub fn add_npm_script_to_package(
         serde_json::Value::String(script.clone()),
     );
 
-    Ok(serde_json::to_string_pretty(&json)?)
+    check_package_json_change(
+        package_json,
+        to_string_preserving_format(&package_json.1, &json)?,
+        format!("add script {script_name}"),
+        |json| {
+            json.get("scripts")
+                .and_then(|scripts| scripts.get(script_name))
+                .is_some_and(|value| value.eq(script))
+        },
+    )
 }
 
 pub fn get_name_of_root_package(file_tree: &FileTree) -> Result<String, NpmScaffoldingUtilsError> {
@@ -270,59 +573,237 @@ pub fn get_name_of_root_package(file_tree: &FileTree) -> Result<String, NpmScaff
     Ok(name.clone())
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum PackageManager {
-	Npm,
-	Pnpm,
-	Yarn
+    Npm,
+    Pnpm,
+    Yarn,
+    Bun,
 }
 impl ToString for PackageManager {
-	fn to_string(&self) -> String {
-      format!("{self:?}")
-  }
+    fn to_string(&self) -> String {
+        format!("{self:?}")
+    }
 }
 
 impl PackageManager {
-	pub fn all_package_managers() -> Vec<PackageManager> {
-		vec![PackageManager::Npm, PackageManager::Pnpm, PackageManager::Yarn]
-	}
-	pub fn run_script_command(&self, script: String, workspace: Option<String>) -> String {
-		let workspace_selection = match workspace {
-			None => format!(""),
-			Some(workspace_name) => match self {
-				PackageManager::Npm => format!(" -w {workspace_name}"),
-				PackageManager::Pnpm => format!(" -F {workspace_name}"),
-				PackageManager::Yarn => format!(" -w {workspace_name}"),
-			}
-		};
-		match self {
-			PackageManager::Npm => format!("npm run{workspace_selection} {script}"),
-			PackageManager::Pnpm => format!("pnpm{workspace_selection} {script}"),
-			PackageManager::Yarn=> format!("yarn{workspace_selection} {script}")
-		}
-	}
-}
-
-pub fn guess_or_choose_package_manager(file_tree: &FileTree) -> Result<PackageManager, NpmScaffoldingUtilsError> {
-	if file_exists(&file_tree, PathBuf::from("package-lock.json").as_path()) {
-		return Ok(PackageManager::Npm);
-	}
-	if file_exists(&file_tree, PathBuf::from("pnpm-lock.yaml").as_path()) {
-		return Ok(PackageManager::Pnpm);
-	}
-	if file_exists(&file_tree, PathBuf::from("yarn.lock").as_path()) {
-		return Ok(PackageManager::Yarn);
-	}
-let package_managers = PackageManager::all_package_managers();
-	
-    let index = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(
-        format!(    
-"Could not guess which package manager you are using for this app. Please select the package manager you are using:"))
-        .items(&package_managers[..])
-        .interact()?;
-
-      Ok(package_managers[index])
+    pub fn all_package_managers() -> Vec<PackageManager> {
+        vec![
+            PackageManager::Npm,
+            PackageManager::Pnpm,
+            PackageManager::Yarn,
+            PackageManager::Bun,
+        ]
+    }
+    /// Arguments that select the package of the workspace with the given name for a command
+    pub fn workspace_filter(&self, workspace: &String) -> String {
+        match self {
+            PackageManager::Npm => format!("-w {workspace}"),
+            PackageManager::Pnpm => format!("-F {workspace}"),
+            PackageManager::Yarn => format!("workspace {workspace}"),
+            PackageManager::Bun => format!("--filter {workspace}"),
+        }
+    }
+    fn workspace_selection(&self, workspace: Option<String>) -> String {
+        match workspace {
+            None => String::new(),
+            Some(workspace_name) => format!(" {}", self.workspace_filter(&workspace_name)),
+        }
+    }
+    pub fn install_command(&self) -> String {
+        format!("{} install", self.program())
+    }
+    /// Command to add a dependency to the given package of the workspace, or to the package in the current directory
+    ///
+    /// Errors for Bun when a package of the workspace is given, since `bun add` can't select it
+    pub fn add_dependency_command(
+        &self,
+        name: String,
+        spec: Option<String>,
+        dev: bool,
+        workspace: Option<String>,
+    ) -> Result<String, NpmScaffoldingUtilsError> {
+        let dev_flag = match dev {
+            true => " -D",
+            false => "",
+        };
+        let dependency = match spec {
+            None => name,
+            Some(spec) => shell_quote(format!("{name}@{spec}")),
+        };
+        if *self == PackageManager::Bun && workspace.is_some() {
+            return Err(NpmScaffoldingUtilsError::UnsupportedWorkspaceCommandError(
+                self.to_string(),
+                format!("bun add{dev_flag} {dependency}"),
+            ));
+        }
+        let workspace_selection = self.workspace_selection(workspace);
+        Ok(match self {
+            PackageManager::Npm => {
+                format!("npm install{workspace_selection}{dev_flag} {dependency}")
+            }
+            PackageManager::Pnpm => format!("pnpm{workspace_selection} add{dev_flag} {dependency}"),
+            PackageManager::Yarn => format!("yarn{workspace_selection} add{dev_flag} {dependency}"),
+            PackageManager::Bun => format!("bun add{dev_flag} {dependency}"),
+        })
+    }
+    /// Command to run a binary installed in the dependencies, like `tsc`
+    ///
+    /// Errors for Bun when a package of the workspace is given, since `bun x` can't select it
+    pub fn exec_command(
+        &self,
+        command: String,
+        workspace: Option<String>,
+    ) -> Result<String, NpmScaffoldingUtilsError> {
+        if *self == PackageManager::Bun && workspace.is_some() {
+            return Err(NpmScaffoldingUtilsError::UnsupportedWorkspaceCommandError(
+                self.to_string(),
+                format!("bun x {command}"),
+            ));
+        }
+        let workspace_selection = self.workspace_selection(workspace);
+        Ok(match self {
+            PackageManager::Npm => format!("npm exec{workspace_selection} -- {command}"),
+            PackageManager::Pnpm => format!("pnpm{workspace_selection} exec {command}"),
+            PackageManager::Yarn => format!("yarn{workspace_selection} exec {command}"),
+            PackageManager::Bun => format!("bun x {command}"),
+        })
+    }
+    pub fn run_script_command(&self, script: String, workspace: Option<String>) -> String {
+        let workspace_selection = self.workspace_selection(workspace);
+        match self {
+            PackageManager::Npm => format!("npm run{workspace_selection} {script}"),
+            PackageManager::Pnpm => format!("pnpm{workspace_selection} {script}"),
+            PackageManager::Yarn => format!("yarn{workspace_selection} {script}"),
+            PackageManager::Bun => format!("bun run{workspace_selection} {script}"),
+        }
+    }
+
+    /// Name of the executable of the package manager
+    pub fn program(&self) -> String {
+        self.to_string().to_lowercase()
+    }
+
+    /// Lock files that each package manager writes next to the package.json of the workspace root
+    fn lock_files(&self) -> Vec<&'static str> {
+        match self {
+            PackageManager::Npm => vec!["package-lock.json"],
+            PackageManager::Pnpm => vec!["pnpm-lock.yaml"],
+            PackageManager::Yarn => vec!["yarn.lock"],
+            PackageManager::Bun => vec!["bun.lockb", "bun.lock"],
+        }
+    }
+
+    /// Parses the `packageManager` field of a package.json, like "pnpm@9.0.0", as used by corepack
+    fn from_package_manager_field(package_manager_field: &str) -> Option<PackageManager> {
+        let name = package_manager_field
+            .split_once('@')
+            .map(|(name, _version)| name)
+            .unwrap_or(package_manager_field);
+
+        PackageManager::all_package_managers()
+            .into_iter()
+            .find(|package_manager| package_manager.to_string().to_lowercase().eq(name))
+    }
+}
+
+/// Quotes the given argument for a POSIX shell if it contains characters that the shell would interpret,
+/// like the "&" and "#" of git dependency sources
+fn shell_quote(argument: String) -> String {
+    let is_safe = argument
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "@/._-:+=,".contains(c));
+    match is_safe {
+        true => argument,
+        false => format!("'{}'", argument.replace('\'', r"'\''")),
+    }
+}
+
+/// Guesses the package manager of the workspace in the given directory of the file system,
+/// from the `packageManager` field of its package.json or from its lock file
+pub fn guess_package_manager_in_directory(path: &Path) -> Option<PackageManager> {
+    let package_manager_field = std::fs::read_to_string(path.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|json| {
+            json.get("packageManager")
+                .and_then(|field| field.as_str())
+                .and_then(PackageManager::from_package_manager_field)
+        });
+    if package_manager_field.is_some() {
+        return package_manager_field;
+    }
+
+    find_lock_file_package_manager(path, Path::new(""))
+}
+
+/// Finds the package manager of the closest lock file in the given directory of the repository or any of its parents
+///
+/// Lock files are looked up in the file system instead of in the file tree, since binary ones
+/// like `bun.lockb` can't be loaded into it
+fn find_lock_file_package_manager(
+    repository_path: &Path,
+    package_dir: &Path,
+) -> Option<PackageManager> {
+    package_dir.ancestors().find_map(|dir| {
+        PackageManager::all_package_managers()
+            .into_iter()
+            .find(|package_manager| {
+                package_manager
+                    .lock_files()
+                    .into_iter()
+                    .any(|lock_file| repository_path.join(dir).join(lock_file).exists())
+            })
+    })
+}
+
+pub fn guess_or_choose_package_manager(
+    file_tree: &FileTree,
+    repository_path: &Path,
+    chooser: &dyn Chooser,
+) -> Result<PackageManager, NpmScaffoldingUtilsError> {
+    guess_or_choose_package_manager_for_package(file_tree, repository_path, Path::new(""), chooser)
+}
+
+/// Guesses the package manager of the workspace that the package in the given directory belongs to:
+/// from the `packageManager` field of the root package.json, or from the closest lock file in the directory
+/// of the package or any of its parents in the repository at the given path, prompting for it if none of them is found
+pub fn guess_or_choose_package_manager_for_package(
+    file_tree: &FileTree,
+    repository_path: &Path,
+    package_dir: &Path,
+    chooser: &dyn Chooser,
+) -> Result<PackageManager, NpmScaffoldingUtilsError> {
+    let root_package_json_path = PathBuf::from("package.json");
+    if file_exists(file_tree, &root_package_json_path) {
+        let root_package_json: Value =
+            serde_json::from_str(&file_content(file_tree, &root_package_json_path)?)?;
+        if let Some(package_manager) = root_package_json
+            .get("packageManager")
+            .and_then(|field| field.as_str())
+            .and_then(PackageManager::from_package_manager_field)
+        {
+            return Ok(package_manager);
+        }
+    }
+
+    if let Some(package_manager) = find_lock_file_package_manager(repository_path, package_dir) {
+        return Ok(package_manager);
+    }
+
+    let package_managers = PackageManager::all_package_managers();
+    let package_managers_names: Vec<String> = package_managers
+        .iter()
+        .map(|package_manager| package_manager.to_string())
+        .collect();
+
+    let index = chooser.choose(
+        &String::from("Could not guess which package manager you are using for this app. Please select the package manager you are using:"),
+        &package_managers_names,
+        0,
+    )?;
+
+    Ok(package_managers[index])
 }
 
 #[cfg(test)]
@@ -330,6 +811,7 @@ mod tests {
     use super::*;
     use build_fs_tree::{dir, file};
     use file_tree_utils::file_content;
+    use prompt_utils::{DefaultChooser, DialoguerChooser, ScriptedChooser};
 
     #[test]
     fn single_package_test() {
@@ -337,17 +819,91 @@ mod tests {
             "package.json" => file!(empty_package_json("single"))
         };
 
+        let repo = add_npm_dependency(
+            repo,
+            "some-dep".into(),
+            "some-url".into(),
+            DependencyKind::Regular,
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("package.json").as_path()).unwrap(),
+            r#"{
+  "name": "single",
+  "dependencies": {
+    "some-dep": "some-url"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn other_dependency_sources_test() {
+        let mut repo: FileTree = dir! {
+            "package.json" => file!(empty_package_json("single"))
+        };
+
+        for (dependency, source) in [
+            ("profiles", "holochain-open-dev/profiles#main"),
+            ("ui", "../ui"),
+            ("lit", "catalog:"),
+            ("@std/path", "jsr:@std/path@^1.0.0"),
+            ("elements", "next_v2"),
+        ] {
+            repo = add_npm_dependency(
+                repo,
+                dependency.into(),
+                source.into(),
+                DependencyKind::Regular,
+                None,
+                &DialoguerChooser::new(true),
+            )
+            .unwrap();
+        }
         let repo =
-            add_npm_dependency(repo, "some-dep".into(), "some-url".into(), None, None).unwrap();
+            update_npm_dependency_source(repo, "ui".into(), "portal:../ui".into(), None).unwrap();
 
         assert_eq!(
             file_content(&repo, PathBuf::from("package.json").as_path()).unwrap(),
             r#"{
   "name": "single",
+  "dependencies": {
+    "profiles": "holochain-open-dev/profiles#main",
+    "ui": "portal:../ui",
+    "lit": "catalog:",
+    "@std/path": "jsr:@std/path@^1.0.0",
+    "elements": "next_v2"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn package_without_dependencies_test() {
+        let content = add_npm_dependency_to_package(
+            &(
+                PathBuf::from("package.json"),
+                String::from("{\n  \"name\": \"single\"\n}\n"),
+            ),
+            &String::from("some-dep"),
+            &String::from("some-url"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            content,
+            r#"{
+  "name": "single",
   "dependencies": {
     "some-dep": "some-url"
   }
-}"#
+}
+"#
         );
     }
 
@@ -364,7 +920,37 @@ mod tests {
   "devDependencies": {
     "some-dep": "some-url"
   }
-}"#
+}
+"#
+        );
+    }
+
+    #[test]
+    fn single_package_peer_dependency_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(empty_package_json("single"))
+        };
+
+        let repo = add_npm_dependency(
+            repo,
+            "some-dep".into(),
+            "^0.1.0".into(),
+            DependencyKind::Peer,
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("package.json").as_path()).unwrap(),
+            r#"{
+  "name": "single",
+  "dependencies": {},
+  "peerDependencies": {
+    "some-dep": "^0.1.0"
+  }
+}
+"#
         );
     }
 
@@ -382,6 +968,7 @@ mod tests {
     fn multiple_package_test() {
         let repo: FileTree = dir! {
             "package.json" => file!(empty_package_json("root")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
             "packages" => dir! {
                 "package1" => dir! {
                     "package.json" => file!(empty_package_json("package1"))
@@ -396,8 +983,9 @@ mod tests {
             repo,
             "some-dep".into(),
             "some-url".into(),
+            DependencyKind::Regular,
             Some(String::from("package1")),
-            None,
+            &DialoguerChooser::new(true),
         )
         .unwrap();
 
@@ -412,7 +1000,311 @@ mod tests {
   "dependencies": {
     "some-dep": "some-url"
   }
-}"#
+}
+"#
+        );
+    }
+
+    #[test]
+    fn multiple_package_non_interactive_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(empty_package_json("root")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
+            "packages" => dir! {
+                "package1" => dir! {
+                    "package.json" => file!(empty_package_json("package1"))
+                }
+            }
+        };
+
+        let result = add_npm_dependency(
+            repo,
+            "some-dep".into(),
+            "some-url".into(),
+            DependencyKind::Regular,
+            None,
+            &DialoguerChooser::new(true),
+        );
+
+        let Err(NpmScaffoldingUtilsError::PromptError(PromptError::NonInteractiveError(
+            _prompt,
+            choices,
+        ))) = result
+        else {
+            panic!("expected a non interactive error");
+        };
+        assert_eq!(
+            choices,
+            vec![String::from("root"), String::from("package1")]
+        );
+    }
+
+    #[test]
+    fn multiple_package_chooser_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(empty_package_json("root")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
+            "packages" => dir! {
+                "package1-dev" => dir! {
+                    "package.json" => file!(empty_package_json("package1-dev"))
+                },
+                "package2" => dir! {
+                    "package.json" => file!(empty_package_json("package2"))
+                }
+            }
+        };
+
+        let scripted = add_npm_dependency(
+            repo.clone(),
+            "some-dep".into(),
+            "some-url".into(),
+            DependencyKind::Regular,
+            None,
+            &ScriptedChooser::new(vec!["package1-dev".into()]),
+        )
+        .unwrap();
+        assert!(file_content(
+            &scripted,
+            PathBuf::from("packages/package1-dev/package.json").as_path()
+        )
+        .unwrap()
+        .contains("some-dep"));
+
+        let defaulted = add_npm_dependency(
+            repo,
+            "some-dep".into(),
+            "some-url".into(),
+            DependencyKind::Regular,
+            None,
+            &DefaultChooser,
+        )
+        .unwrap();
+        assert!(
+            file_content(&defaulted, PathBuf::from("package.json").as_path())
+                .unwrap()
+                .contains("some-dep")
         );
     }
+
+    #[test]
+    fn remove_and_update_dependency_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from(r#"{
+  "name": "root",
+  "private": true,
+  "devDependencies": {
+    "some-dep": "some-url"
+  }
+}
+"#)),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
+            "packages" => dir! {
+                "package1" => dir! {
+                    "package.json" => file!(String::from(r#"{
+  "name": "package1",
+  "dependencies": {
+    "other-dep": "other-url",
+    "some-dep": "some-url"
+  },
+  "peerDependencies": {
+    "some-dep": "some-url"
+  }
+}
+"#))
+                },
+                "package2" => dir! {
+                    "package.json" => file!(empty_package_json("package2"))
+                }
+            }
+        };
+
+        let updated = update_npm_dependency_source(
+            repo.clone(),
+            "some-dep".into(),
+            "new-url".into(),
+            Some("package1".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            file_content(
+                &updated,
+                PathBuf::from("packages/package1/package.json").as_path()
+            )
+            .unwrap(),
+            r#"{
+  "name": "package1",
+  "dependencies": {
+    "other-dep": "other-url",
+    "some-dep": "new-url"
+  },
+  "peerDependencies": {
+    "some-dep": "new-url"
+  }
+}
+"#
+        );
+        assert!(
+            file_content(&updated, PathBuf::from("package.json").as_path())
+                .unwrap()
+                .contains("some-url")
+        );
+
+        let removed = remove_npm_dependency(repo, "some-dep".into(), None).unwrap();
+        assert_eq!(
+            file_content(&removed, PathBuf::from("package.json").as_path()).unwrap(),
+            r#"{
+  "name": "root",
+  "private": true,
+  "devDependencies": {}
+}
+"#
+        );
+        assert_eq!(
+            file_content(
+                &removed,
+                PathBuf::from("packages/package1/package.json").as_path()
+            )
+            .unwrap(),
+            r#"{
+  "name": "package1",
+  "dependencies": {
+    "other-dep": "other-url"
+  },
+  "peerDependencies": {}
+}
+"#
+        );
+
+        assert!(matches!(
+            remove_npm_dependency(removed, "some-dep".into(), None),
+            Err(NpmScaffoldingUtilsError::NpmDependencyNotFoundError(_))
+        ));
+    }
+
+    #[test]
+    fn guess_package_manager_test() {
+        let repository_path =
+            std::env::temp_dir().join(format!("guess_package_manager_test_{}", std::process::id()));
+        let ui_path = repository_path.join("app").join("packages").join("ui");
+        std::fs::create_dir_all(&ui_path).unwrap();
+        std::fs::write(
+            repository_path.join("package.json"),
+            empty_package_json("root"),
+        )
+        .unwrap();
+        std::fs::write(ui_path.join("package.json"), empty_package_json("ui")).unwrap();
+        // Not valid UTF-8, like the binary lock files that Bun writes
+        std::fs::write(
+            repository_path.join("app").join("bun.lockb"),
+            [0x23, 0x21, 0x2f, 0xff, 0xfe, 0x00, 0x01],
+        )
+        .unwrap();
+
+        let repo = file_tree_utils::load_directory_into_memory(&repository_path).unwrap();
+        let package_manager = guess_or_choose_package_manager_for_package(
+            &repo,
+            &repository_path,
+            Path::new("app/packages/ui"),
+            &DialoguerChooser::new(true),
+        );
+        std::fs::remove_dir_all(&repository_path).unwrap();
+
+        assert_eq!(package_manager.unwrap(), PackageManager::Bun);
+        assert_eq!(
+            PackageManager::Bun.run_script_command("start".into(), Some("ui".into())),
+            "bun run --filter ui start"
+        );
+
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from(r#"{ "name": "root", "packageManager": "yarn@4.1.0" }"#)),
+        };
+
+        assert_eq!(
+            guess_or_choose_package_manager(&repo, &repository_path, &DialoguerChooser::new(true))
+                .unwrap(),
+            PackageManager::Yarn
+        );
+    }
+
+    #[test]
+    fn package_manager_commands_test() {
+        assert_eq!(PackageManager::Yarn.install_command(), "yarn install");
+        assert_eq!(
+            PackageManager::Npm
+                .add_dependency_command(
+                    "typescript".into(),
+                    Some("^5.4.0".into()),
+                    true,
+                    Some("ui".into())
+                )
+                .unwrap(),
+            "npm install -w ui -D 'typescript@^5.4.0'"
+        );
+        assert_eq!(
+            PackageManager::Pnpm
+                .add_dependency_command(
+                    "@holochain-open-dev/profiles".into(),
+                    Some("github:holochain-open-dev/profiles#main&path:ui".into()),
+                    false,
+                    None
+                )
+                .unwrap(),
+            "pnpm add '@holochain-open-dev/profiles@github:holochain-open-dev/profiles#main&path:ui'"
+        );
+        assert_eq!(
+            PackageManager::Pnpm
+                .exec_command("tsc".into(), Some("ui".into()))
+                .unwrap(),
+            "pnpm -F ui exec tsc"
+        );
+
+        assert_eq!(
+            PackageManager::Yarn
+                .add_dependency_command("lit".into(), None, false, Some("ui".into()))
+                .unwrap(),
+            "yarn workspace ui add lit"
+        );
+        assert_eq!(
+            PackageManager::Yarn
+                .exec_command("tsc".into(), Some("ui".into()))
+                .unwrap(),
+            "yarn workspace ui exec tsc"
+        );
+        assert_eq!(
+            PackageManager::Yarn.run_script_command("start".into(), Some("ui".into())),
+            "yarn workspace ui start"
+        );
+
+        assert_eq!(
+            PackageManager::Bun
+                .add_dependency_command("typescript".into(), None, true, None)
+                .unwrap(),
+            "bun add -D typescript"
+        );
+        assert_eq!(
+            PackageManager::Bun
+                .exec_command("tsc".into(), None)
+                .unwrap(),
+            "bun x tsc"
+        );
+        assert!(matches!(
+            PackageManager::Bun.add_dependency_command(
+                "lit".into(),
+                None,
+                false,
+                Some("ui".into())
+            ),
+            Err(NpmScaffoldingUtilsError::UnsupportedWorkspaceCommandError(
+                _,
+                _
+            ))
+        ));
+        assert!(matches!(
+            PackageManager::Bun.exec_command("tsc".into(), Some("ui".into())),
+            Err(NpmScaffoldingUtilsError::UnsupportedWorkspaceCommandError(
+                _,
+                _
+            ))
+        ));
+    }
 }
@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+
+use file_tree_utils::{create_dir_all, file_exists, insert_file, FileTree};
+use serde_json::{Map, Value};
+
+use crate::{
+    add_npm_dependency_to_package, add_npm_dev_dependency_to_package, add_npm_script_to_package,
+    add_npm_workspace_member, NpmDependencySpec, NpmScaffoldingUtilsError,
+};
+
+/// Contents of the package.json of a package created by `scaffold_npm_package`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpmPackageTemplate {
+    pub name: String,
+    pub version: String,
+    /// "module" for ES modules, or "commonjs"
+    pub module_type: String,
+    /// Entry point of the package, exported as ".", like "./dist/index.js"
+    pub exports: Option<String>,
+    /// Scripts of the package, by name
+    pub scripts: Vec<(String, String)>,
+    /// Dependencies of the package, with their sources
+    pub dependencies: Vec<(String, String)>,
+    /// Dev dependencies of the package, with their sources
+    pub dev_dependencies: Vec<(String, String)>,
+}
+
+impl NpmPackageTemplate {
+    /// An ES module package without exports, scripts or dependencies
+    pub fn new(name: String) -> Self {
+        NpmPackageTemplate {
+            name,
+            version: String::from("0.0.0"),
+            module_type: String::from("module"),
+            exports: None,
+            scripts: vec![],
+            dependencies: vec![],
+            dev_dependencies: vec![],
+        }
+    }
+}
+
+/// Creates a new NPM package in the given directory from the given template, and adds it to the members
+/// of the NPM workspace of the repository
+pub fn scaffold_npm_package(
+    mut file_tree: FileTree,
+    package_dir: PathBuf,
+    template: NpmPackageTemplate,
+) -> Result<FileTree, NpmScaffoldingUtilsError> {
+    let package_json_path = package_dir.join("package.json");
+    if file_exists(&file_tree, &package_json_path) {
+        return Err(NpmScaffoldingUtilsError::NpmPackageAlreadyExistsError(
+            package_json_path,
+        ));
+    }
+    for (_dependency, source) in template
+        .dependencies
+        .iter()
+        .chain(&template.dev_dependencies)
+    {
+        NpmDependencySpec::parse(source)?;
+    }
+
+    let mut json = Map::new();
+    json.insert("name".into(), Value::String(template.name.clone()));
+    json.insert("version".into(), Value::String(template.version));
+    json.insert("type".into(), Value::String(template.module_type));
+    if let Some(exports) = template.exports {
+        json.insert(
+            "exports".into(),
+            Value::Object(Map::from_iter([(".".into(), Value::String(exports))])),
+        );
+    }
+    let mut content = serde_json::to_string_pretty(&Value::Object(json))? + "\n";
+
+    for (script_name, script) in &template.scripts {
+        content =
+            add_npm_script_to_package(&(package_json_path.clone(), content), script_name, script)?;
+    }
+    for (dependency, source) in &template.dependencies {
+        content = add_npm_dependency_to_package(
+            &(package_json_path.clone(), content),
+            dependency,
+            source,
+        )?;
+    }
+    for (dev_dependency, source) in &template.dev_dependencies {
+        content = add_npm_dev_dependency_to_package(
+            &(package_json_path.clone(), content),
+            dev_dependency,
+            source,
+        )?;
+    }
+
+    create_dir_all(&mut file_tree, &package_dir)?;
+    insert_file(&mut file_tree, &package_json_path, &content)?;
+    eprintln!("Created NPM package {} in {package_dir:?}.", template.name);
+
+    add_npm_workspace_member(file_tree, package_dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use build_fs_tree::{dir, file};
+    use file_tree_utils::file_content;
+    use std::path::Path;
+
+    #[test]
+    fn scaffold_npm_package_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from("{\n  \"name\": \"root\",\n  \"private\": true\n}\n")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'ui'\n")),
+        };
+
+        let mut template = NpmPackageTemplate::new("@my-app/profiles".into());
+        template.exports = Some("./dist/index.js".into());
+        template.scripts = vec![("build".into(), "tsc".into())];
+        template.dependencies = vec![("lit".into(), "^3.0.0".into())];
+        template.dev_dependencies = vec![("typescript".into(), "^5.4.0".into())];
+
+        let repo = scaffold_npm_package(repo, PathBuf::from("modules/profiles"), template.clone())
+            .unwrap();
+
+        assert_eq!(
+            file_content(&repo, Path::new("modules/profiles/package.json")).unwrap(),
+            r#"{
+  "name": "@my-app/profiles",
+  "version": "0.0.0",
+  "type": "module",
+  "exports": {
+    ".": "./dist/index.js"
+  },
+  "scripts": {
+    "build": "tsc"
+  },
+  "dependencies": {
+    "lit": "^3.0.0"
+  },
+  "devDependencies": {
+    "typescript": "^5.4.0"
+  }
+}
+"#
+        );
+        assert_eq!(
+            file_content(&repo, Path::new("pnpm-workspace.yaml")).unwrap(),
+            "packages:\n  - 'ui'\n  - 'modules/profiles'\n"
+        );
+
+        assert!(matches!(
+            scaffold_npm_package(repo, PathBuf::from("modules/profiles"), template),
+            Err(NpmScaffoldingUtilsError::NpmPackageAlreadyExistsError(_))
+        ));
+    }
+}
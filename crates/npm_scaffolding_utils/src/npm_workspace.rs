@@ -0,0 +1,573 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use file_tree_utils::{
+    file_content, file_exists, find_files_by_name, insert_file, map_file, FileTree,
+};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{to_string_preserving_format, NpmScaffoldingUtilsError};
+
+/// Where the members of the workspace are declared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NpmWorkspaceKind {
+    /// In the `packages` list of `pnpm-workspace.yaml`
+    Pnpm,
+    /// In the `workspaces` field of the root `package.json`, used by npm and yarn
+    PackageJson,
+}
+
+/// The NPM packages of a repository, as declared by its workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpmWorkspace {
+    /// `None` if the repository doesn't declare a workspace
+    pub kind: Option<NpmWorkspaceKind>,
+    pub root_package_json: Option<(PathBuf, String)>,
+    /// Glob patterns for the directories of the members, as declared in the workspace
+    pub member_patterns: Vec<String>,
+    /// `package.json` files of the members of the workspace, other than the root package
+    ///
+    /// If there is no workspace, all the packages found in the repository are considered members
+    pub members: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Deserialize)]
+struct PnpmWorkspaceManifest {
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+impl NpmWorkspace {
+    /// Packages that dependencies can be added to: the members of the workspace,
+    /// and its root package unless it's a private workspace root
+    ///
+    /// Without a workspace, that's only the root package, or all the packages if there is no root package
+    pub fn candidate_packages(&self) -> BTreeMap<PathBuf, String> {
+        let mut candidates = self.members.clone();
+
+        if let Some((root_path, root_content)) = &self.root_package_json {
+            if self.kind.is_none() {
+                return BTreeMap::from([(root_path.clone(), root_content.clone())]);
+            }
+            if !is_private_package(root_content) {
+                candidates.insert(root_path.clone(), root_content.clone());
+            }
+        }
+
+        candidates
+    }
+
+    /// All the packages of the repository: the members of the workspace and its root package
+    pub fn all_packages(&self) -> BTreeMap<PathBuf, String> {
+        let mut packages = self.members.clone();
+
+        if let Some((root_path, root_content)) = &self.root_package_json {
+            packages.insert(root_path.clone(), root_content.clone());
+        }
+
+        packages
+    }
+}
+
+/// Finds the NPM workspace of the repository, from its `pnpm-workspace.yaml`
+/// or from the `workspaces` field of its root `package.json`
+pub fn find_npm_workspace(file_tree: &FileTree) -> Result<NpmWorkspace, NpmScaffoldingUtilsError> {
+    let root_package_json_path = PathBuf::from("package.json");
+    let root_package_json = match file_exists(file_tree, &root_package_json_path) {
+        true => Some((
+            root_package_json_path.clone(),
+            file_content(file_tree, &root_package_json_path)?,
+        )),
+        false => None,
+    };
+
+    let pnpm_workspace_path = PathBuf::from("pnpm-workspace.yaml");
+    let (kind, member_patterns) = if file_exists(file_tree, &pnpm_workspace_path) {
+        let pnpm_workspace: PnpmWorkspaceManifest =
+            serde_yaml::from_str(&file_content(file_tree, &pnpm_workspace_path)?)?;
+        (Some(NpmWorkspaceKind::Pnpm), pnpm_workspace.packages)
+    } else {
+        match &root_package_json {
+            Some(root_package_json) => match package_json_workspaces(root_package_json)? {
+                Some(patterns) => (Some(NpmWorkspaceKind::PackageJson), patterns),
+                None => (None, vec![]),
+            },
+            None => (None, vec![]),
+        }
+    };
+
+    let package_jsons: BTreeMap<PathBuf, String> =
+        find_files_by_name(file_tree, &PathBuf::from("package.json"))
+            .into_iter()
+            .filter(|(path, _)| {
+                !path
+                    .components()
+                    .any(|component| component.as_os_str() == "node_modules")
+            })
+            .filter(|(path, _)| path.ne(&root_package_json_path))
+            .collect();
+
+    let members = match kind {
+        None => package_jsons,
+        Some(_) => {
+            let (included, excluded) = build_member_glob_sets(&member_patterns)?;
+            package_jsons
+                .into_iter()
+                .filter(|(path, _)| {
+                    let package_dir = path.parent().unwrap_or(Path::new(""));
+                    included.is_match(package_dir) && !excluded.is_match(package_dir)
+                })
+                .collect()
+        }
+    };
+
+    Ok(NpmWorkspace {
+        kind,
+        root_package_json,
+        member_patterns,
+        members,
+    })
+}
+
+/// Adds the given directory to the members of the NPM workspace of the repository, unless its patterns already include it
+///
+/// The member is added to `pnpm-workspace.yaml` for pnpm workspaces, and to the `workspaces` field of the root
+/// `package.json` for npm and yarn workspaces. If there is no workspace, a `pnpm-workspace.yaml` is created
+/// when the repository uses pnpm or has no root package, and the `workspaces` field is added otherwise
+pub fn add_npm_workspace_member(
+    mut file_tree: FileTree,
+    new_workspace_member: String,
+) -> Result<FileTree, NpmScaffoldingUtilsError> {
+    let new_workspace_member = new_workspace_member
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string();
+    let workspace = find_npm_workspace(&file_tree)?;
+
+    if workspace.kind.is_some() {
+        let (included, excluded) = build_member_glob_sets(&workspace.member_patterns)?;
+        if included.is_match(&new_workspace_member) && !excluded.is_match(&new_workspace_member) {
+            return Ok(file_tree);
+        }
+    }
+
+    let pnpm_workspace_path = PathBuf::from("pnpm-workspace.yaml");
+    let root_package_json_path = PathBuf::from("package.json");
+
+    let kind = match workspace.kind {
+        Some(kind) => kind,
+        None => {
+            let uses_pnpm = file_exists(&file_tree, Path::new("pnpm-lock.yaml"));
+            if uses_pnpm || workspace.root_package_json.is_none() {
+                insert_file(
+                    &mut file_tree,
+                    &pnpm_workspace_path,
+                    &format!("packages:\n  - '{new_workspace_member}'\n"),
+                )?;
+                eprintln!(
+                    "Created {pnpm_workspace_path:?} with the member {new_workspace_member}."
+                );
+                return Ok(file_tree);
+            }
+            NpmWorkspaceKind::PackageJson
+        }
+    };
+
+    let changed_file = match kind {
+        NpmWorkspaceKind::Pnpm => {
+            map_file(&mut file_tree, &pnpm_workspace_path, |content| {
+                add_pnpm_workspace_package(&content, &new_workspace_member)
+            })?;
+            pnpm_workspace_path
+        }
+        NpmWorkspaceKind::PackageJson => {
+            map_file(&mut file_tree, &root_package_json_path, |content| {
+                add_package_json_workspace(
+                    &(root_package_json_path.clone(), content),
+                    &new_workspace_member,
+                )
+            })?;
+            root_package_json_path
+        }
+    };
+    eprintln!("Added {new_workspace_member} to the workspace members in {changed_file:?}.");
+
+    Ok(file_tree)
+}
+
+/// Appends the given package to the `packages` list of the given `pnpm-workspace.yaml`, keeping the indentation
+/// and the quotes of the existing items and the rest of the file untouched
+///
+/// Errors if the list is not written in the block form, since editing it would mean reformatting the file
+fn add_pnpm_workspace_package(
+    content: &String,
+    new_package: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    let packages_line = lines
+        .iter()
+        .position(|line| line.trim_end().eq("packages:"));
+
+    let Some(packages_line) = packages_line else {
+        // Other forms of the list, like "packages: ['ui']", can't be edited without reformatting the file
+        let manifest: serde_yaml::Mapping = match content.trim().is_empty() {
+            true => serde_yaml::Mapping::new(),
+            false => serde_yaml::from_str(content)?,
+        };
+        if manifest.contains_key("packages") {
+            return Err(NpmScaffoldingUtilsError::PnpmWorkspaceNotEditableError(
+                new_package.clone(),
+            ));
+        }
+        let separator = match content.is_empty() || content.ends_with('\n') {
+            true => "",
+            false => "\n",
+        };
+        return Ok(format!(
+            "{content}{separator}packages:\n  - '{new_package}'\n"
+        ));
+    };
+
+    let mut last_item = None;
+    for (i, line) in lines.iter().enumerate().skip(packages_line + 1) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("- ") {
+            last_item = Some(i);
+        } else if !(trimmed.is_empty() || trimmed.starts_with('#')) {
+            break;
+        }
+    }
+
+    let new_line = match last_item {
+        Some(last_item) => {
+            let item = &lines[last_item];
+            let indentation = &item[..item.len() - item.trim_start().len()];
+            let value = item.trim_start()[2..].trim_start();
+            let quote = match value.chars().next() {
+                Some(quote) if quote == '\'' || quote == '"' => quote.to_string(),
+                _ => String::new(),
+            };
+            format!("{indentation}- {quote}{new_package}{quote}")
+        }
+        None => format!("  - '{new_package}'"),
+    };
+    lines.insert(last_item.unwrap_or(packages_line) + 1, new_line);
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Ok(new_content)
+}
+
+/// Appends the given pattern to the `workspaces` field of the given package.json,
+/// creating it if needed
+fn add_package_json_workspace(
+    package_json: &(PathBuf, String),
+    new_pattern: &String,
+) -> Result<String, NpmScaffoldingUtilsError> {
+    let mut json: Value = serde_json::from_str(&package_json.1)?;
+
+    let Some(map) = json.as_object_mut() else {
+        return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+            package_json.0.clone(),
+            String::from("package.json did not contain a json object at the root level"),
+        ));
+    };
+
+    let workspaces = map.entry("workspaces").or_insert(Value::Array(vec![]));
+    let patterns = match workspaces {
+        Value::Array(patterns) => patterns,
+        Value::Object(workspaces) => match workspaces.get_mut("packages") {
+            Some(Value::Array(patterns)) => patterns,
+            _ => {
+                return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                    package_json.0.clone(),
+                    String::from(r#"the "workspaces" object did not contain a "packages" array"#),
+                ))
+            }
+        },
+        _ => {
+            return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                package_json.0.clone(),
+                String::from(r#"the "workspaces" property is not an array nor an object"#),
+            ))
+        }
+    };
+    patterns.push(Value::String(new_pattern.clone()));
+
+    to_string_preserving_format(&package_json.1, &json)
+}
+
+/// Reads the `workspaces` field of the given package.json, either an array of patterns
+/// or an object with a `packages` array of patterns, as yarn allows
+fn package_json_workspaces(
+    package_json: &(PathBuf, String),
+) -> Result<Option<Vec<String>>, NpmScaffoldingUtilsError> {
+    let json: Value = serde_json::from_str(&package_json.1)?;
+
+    let patterns = match json.get("workspaces") {
+        None => return Ok(None),
+        Some(Value::Array(patterns)) => patterns,
+        Some(Value::Object(workspaces)) => match workspaces.get("packages") {
+            Some(Value::Array(patterns)) => patterns,
+            _ => {
+                return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                    package_json.0.clone(),
+                    String::from(r#"the "workspaces" object did not contain a "packages" array"#),
+                ))
+            }
+        },
+        Some(_) => {
+            return Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                package_json.0.clone(),
+                String::from(r#"the "workspaces" property is not an array nor an object"#),
+            ))
+        }
+    };
+
+    patterns
+        .iter()
+        .map(|pattern| match pattern {
+            Value::String(pattern) => Ok(pattern.clone()),
+            _ => Err(NpmScaffoldingUtilsError::MalformedJsonError(
+                package_json.0.clone(),
+                String::from(r#"the "workspaces" patterns are not all strings"#),
+            )),
+        })
+        .collect::<Result<Vec<String>, NpmScaffoldingUtilsError>>()
+        .map(Some)
+}
+
+/// Builds the glob sets for the included and the excluded (starting with "!") member patterns
+fn build_member_glob_sets(
+    patterns: &Vec<String>,
+) -> Result<(GlobSet, GlobSet), NpmScaffoldingUtilsError> {
+    let mut included = GlobSetBuilder::new();
+    let mut excluded = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let (builder, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (&mut excluded, pattern),
+            None => (&mut included, pattern.as_str()),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|err| {
+                NpmScaffoldingUtilsError::InvalidWorkspacePatternError(
+                    pattern.to_string(),
+                    err.to_string(),
+                )
+            })?;
+        builder.add(glob);
+    }
+
+    let build = |builder: GlobSetBuilder| {
+        builder.build().map_err(|err| {
+            NpmScaffoldingUtilsError::InvalidWorkspacePatternError(
+                patterns.join(", "),
+                err.to_string(),
+            )
+        })
+    };
+
+    Ok((build(included)?, build(excluded)?))
+}
+
+fn is_private_package(package_json_content: &String) -> bool {
+    serde_json::from_str::<Value>(package_json_content)
+        .ok()
+        .and_then(|json| json.get("private").and_then(|private| private.as_bool()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use build_fs_tree::{dir, file};
+
+    #[test]
+    fn pnpm_workspace_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from(r#"{ "name": "root", "private": true }"#)),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'ui'\n  - 'packages/*'\n  - '!packages/fixture'\n")),
+            "ui" => dir! {
+                "package.json" => file!(String::from(r#"{ "name": "ui" }"#)),
+                "node_modules" => dir! {
+                    "dep" => dir! {
+                        "package.json" => file!(String::from(r#"{ "name": "dep" }"#)),
+                    }
+                }
+            },
+            "packages" => dir! {
+                "app" => dir! {
+                    "package.json" => file!(String::from(r#"{ "name": "app" }"#)),
+                    "tests" => dir! {
+                        "package.json" => file!(String::from(r#"{ "name": "nested" }"#)),
+                    }
+                },
+                "fixture" => dir! {
+                    "package.json" => file!(String::from(r#"{ "name": "fixture" }"#)),
+                }
+            }
+        };
+
+        let workspace = find_npm_workspace(&repo).unwrap();
+
+        assert_eq!(workspace.kind, Some(NpmWorkspaceKind::Pnpm));
+        assert_eq!(
+            workspace
+                .candidate_packages()
+                .into_keys()
+                .collect::<Vec<PathBuf>>(),
+            vec![
+                PathBuf::from("packages/app/package.json"),
+                PathBuf::from("ui/package.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn package_json_workspaces_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from(r#"{ "name": "root", "workspaces": { "packages": ["packages/**"] } }"#)),
+            "packages" => dir! {
+                "app" => dir! {
+                    "package.json" => file!(String::from(r#"{ "name": "app" }"#)),
+                    "tests" => dir! {
+                        "package.json" => file!(String::from(r#"{ "name": "tests" }"#)),
+                    }
+                },
+            },
+            "fixtures" => dir! {
+                "package.json" => file!(String::from(r#"{ "name": "fixture" }"#)),
+            }
+        };
+
+        let workspace = find_npm_workspace(&repo).unwrap();
+
+        assert_eq!(workspace.kind, Some(NpmWorkspaceKind::PackageJson));
+        assert_eq!(
+            workspace
+                .candidate_packages()
+                .into_keys()
+                .collect::<Vec<PathBuf>>(),
+            vec![
+                PathBuf::from("package.json"),
+                PathBuf::from("packages/app/package.json"),
+                PathBuf::from("packages/app/tests/package.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn add_npm_workspace_member_test() {
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from(r#"{ "name": "root", "private": true }"#)),
+            "pnpm-workspace.yaml" => file!(String::from("packages: \n  - 'ui'\n\ncatalog:\n  lit: ^3.0.0\n")),
+        };
+
+        let repo = add_npm_workspace_member(repo, "./packages/app/".into()).unwrap();
+        assert_eq!(
+            file_content(&repo, Path::new("pnpm-workspace.yaml")).unwrap(),
+            "packages: \n  - 'ui'\n  - 'packages/app'\n\ncatalog:\n  lit: ^3.0.0\n"
+        );
+
+        let already_member = add_npm_workspace_member(repo, "packages/app".into()).unwrap();
+        assert_eq!(
+            file_content(&already_member, Path::new("pnpm-workspace.yaml")).unwrap(),
+            "packages: \n  - 'ui'\n  - 'packages/app'\n\ncatalog:\n  lit: ^3.0.0\n"
+        );
+
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from("{\n  \"name\": \"root\",\n  \"workspaces\": {\n    \"packages\": [\"ui\"]\n  }\n}\n")),
+        };
+
+        let repo = add_npm_workspace_member(repo, "packages/*".into()).unwrap();
+        assert_eq!(
+            file_content(&repo, Path::new("package.json")).unwrap(),
+            "{\n  \"name\": \"root\",\n  \"workspaces\": {\n    \"packages\": [\"ui\", \"packages/*\"]\n  }\n}\n"
+        );
+        assert_eq!(
+            find_npm_workspace(&repo).unwrap().member_patterns,
+            vec![String::from("ui"), String::from("packages/*")]
+        );
+    }
+
+    #[test]
+    fn add_pnpm_workspace_package_test() {
+        assert_eq!(
+            add_pnpm_workspace_package(
+                &String::from("# Shared versions\ncatalog:\n  lit: ^3.0.0\n"),
+                &String::from("ui")
+            )
+            .unwrap(),
+            "# Shared versions\ncatalog:\n  lit: ^3.0.0\npackages:\n  - 'ui'\n"
+        );
+
+        assert!(matches!(
+            add_pnpm_workspace_package(
+                &String::from("# Members\npackages: ['ui']\n"),
+                &String::from("packages/app")
+            ),
+            Err(NpmScaffoldingUtilsError::PnpmWorkspaceNotEditableError(package))
+                if package == "packages/app"
+        ));
+    }
+
+    #[test]
+    fn add_npm_workspace_member_without_workspace_test() {
+        let repo: FileTree = dir! {
+            "ui" => dir! {
+                "package.json" => file!(String::from(r#"{ "name": "ui" }"#)),
+            }
+        };
+
+        let repo = add_npm_workspace_member(repo, "ui".into()).unwrap();
+        assert_eq!(
+            file_content(&repo, Path::new("pnpm-workspace.yaml")).unwrap(),
+            "packages:\n  - 'ui'\n"
+        );
+
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from("{\n  \"name\": \"root\",\n  \"private\": true\n}\n")),
+            "pnpm-lock.yaml" => file!(String::from("lockfileVersion: '9.0'\n")),
+        };
+
+        let repo = add_npm_workspace_member(repo, "ui".into()).unwrap();
+        assert_eq!(
+            file_content(&repo, Path::new("pnpm-workspace.yaml")).unwrap(),
+            "packages:\n  - 'ui'\n"
+        );
+        assert_eq!(
+            file_content(&repo, Path::new("package.json")).unwrap(),
+            "{\n  \"name\": \"root\",\n  \"private\": true\n}\n"
+        );
+
+        let repo: FileTree = dir! {
+            "package.json" => file!(String::from("{\n  \"name\": \"root\",\n  \"private\": true\n}\n")),
+        };
+
+        let repo = add_npm_workspace_member(repo, "ui".into()).unwrap();
+        assert_eq!(
+            file_content(&repo, Path::new("package.json")).unwrap(),
+            r#"{
+  "name": "root",
+  "private": true,
+  "workspaces": [
+    "ui"
+  ]
+}
+"#
+        );
+        assert!(!file_exists(&repo, Path::new("pnpm-workspace.yaml")));
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use dialoguer::{theme::ColorfulTheme, MultiSelect, Select};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PromptError {
+    #[error(transparent)]
+    DialoguerError(#[from] dialoguer::Error),
+
+    #[error("IO error: {0}")]
+    StdIoError(#[from] std::io::Error),
+
+    #[error("Can't prompt \"{0}\" in non-interactive mode. Valid choices are: {}", .1.join(", "))]
+    NonInteractiveError(String, Vec<String>),
+
+    #[error("No answer left for \"{0}\" in the answers file. Valid choices are: {}", .1.join(", "))]
+    NoAnswerLeftError(String, Vec<String>),
+
+    #[error("Invalid answer \"{0}\" for \"{1}\". Valid choices are: {}", .2.join(", "))]
+    InvalidAnswerError(String, String, Vec<String>),
+}
+
+/// Makes the choices that come up while scaffolding, when there are multiple valid options
+///
+/// Scaffolding functions receive a `&dyn Chooser`, so that they can be driven from a terminal,
+/// from a script or programmatically
+pub trait Chooser {
+    /// Chooses one of the given items, returning its index
+    fn choose(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        default: usize,
+    ) -> Result<usize, PromptError>;
+
+    /// Chooses some of the given items, returning their indexes
+    fn choose_multiple(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        defaults: &Vec<bool>,
+    ) -> Result<Vec<usize>, PromptError>;
+}
+
+/// Whether the user can be prompted: both stdin and stderr, where dialoguer draws its prompts,
+/// need to be a terminal, so that stdout can still be piped
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Prompts the user in the terminal
+///
+/// In non-interactive mode, or if there is no terminal to prompt in, errors with the list of valid choices instead
+pub struct DialoguerChooser {
+    pub non_interactive: bool,
+}
+
+impl DialoguerChooser {
+    pub fn new(non_interactive: bool) -> Self {
+        DialoguerChooser { non_interactive }
+    }
+
+    fn check_interactive(&self, prompt: &String, items: &Vec<String>) -> Result<(), PromptError> {
+        if self.non_interactive || !is_interactive() {
+            return Err(PromptError::NonInteractiveError(
+                prompt.clone(),
+                items.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Chooser for DialoguerChooser {
+    fn choose(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        default: usize,
+    ) -> Result<usize, PromptError> {
+        self.check_interactive(prompt, items)?;
+
+        Ok(Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(default)
+            .items(&items[..])
+            .interact()?)
+    }
+
+    fn choose_multiple(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        defaults: &Vec<bool>,
+    ) -> Result<Vec<usize>, PromptError> {
+        self.check_interactive(prompt, items)?;
+
+        Ok(MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .items(&items[..])
+            .defaults(&defaults[..])
+            .interact()?)
+    }
+}
+
+/// Always chooses the default items
+pub struct DefaultChooser;
+
+impl Chooser for DefaultChooser {
+    fn choose(
+        &self,
+        _prompt: &String,
+        _items: &Vec<String>,
+        default: usize,
+    ) -> Result<usize, PromptError> {
+        Ok(default)
+    }
+
+    fn choose_multiple(
+        &self,
+        _prompt: &String,
+        _items: &Vec<String>,
+        defaults: &Vec<bool>,
+    ) -> Result<Vec<usize>, PromptError> {
+        Ok(defaults
+            .iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(index, _)| index)
+            .collect())
+    }
+}
+
+/// Answers each choice with the next of the given answers, in order
+///
+/// Each answer is the name of the item to choose, or a comma separated list of names for multiple choices
+pub struct ScriptedChooser {
+    answers: RefCell<VecDeque<String>>,
+}
+
+impl ScriptedChooser {
+    pub fn new(answers: Vec<String>) -> Self {
+        ScriptedChooser {
+            answers: RefCell::new(answers.into()),
+        }
+    }
+
+    /// Reads the answers from a file with one answer per line, ignoring empty lines and lines starting with `#`
+    pub fn from_file(path: &Path) -> Result<Self, PromptError> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let answers = contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect();
+
+        Ok(ScriptedChooser::new(answers))
+    }
+
+    fn next_answer(&self, prompt: &String, items: &Vec<String>) -> Result<String, PromptError> {
+        self.answers
+            .borrow_mut()
+            .pop_front()
+            .ok_or(PromptError::NoAnswerLeftError(
+                prompt.clone(),
+                items.clone(),
+            ))
+    }
+
+    fn index_of(answer: &str, prompt: &String, items: &Vec<String>) -> Result<usize, PromptError> {
+        items
+            .iter()
+            .position(|item| item.eq(answer))
+            .ok_or(PromptError::InvalidAnswerError(
+                answer.to_string(),
+                prompt.clone(),
+                items.clone(),
+            ))
+    }
+}
+
+impl Chooser for ScriptedChooser {
+    fn choose(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        _default: usize,
+    ) -> Result<usize, PromptError> {
+        let answer = self.next_answer(prompt, items)?;
+
+        Self::index_of(&answer, prompt, items)
+    }
+
+    fn choose_multiple(
+        &self,
+        prompt: &String,
+        items: &Vec<String>,
+        _defaults: &Vec<bool>,
+    ) -> Result<Vec<usize>, PromptError> {
+        let answer = self.next_answer(prompt, items)?;
+
+        answer
+            .split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| Self::index_of(item, prompt, items))
+            .collect()
+    }
+}
+
+/// Builds the chooser for a command line tool: a scripted one if an answers file was given,
+/// or one that prompts in the terminal otherwise
+pub fn build_chooser(
+    non_interactive: bool,
+    answers_file: Option<PathBuf>,
+) -> Result<Box<dyn Chooser>, PromptError> {
+    match answers_file {
+        Some(answers_file) => Ok(Box::new(ScriptedChooser::from_file(&answers_file)?)),
+        None => Ok(Box::new(DialoguerChooser::new(non_interactive))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_chooser_test() {
+        let chooser = ScriptedChooser::new(vec!["b".into(), "a, c".into()]);
+        let items = vec![String::from("a"), String::from("b"), String::from("c")];
+
+        assert_eq!(chooser.choose(&"".into(), &items, 0).unwrap(), 1);
+        assert_eq!(
+            chooser
+                .choose_multiple(&"".into(), &items, &vec![false; 3])
+                .unwrap(),
+            vec![0, 2]
+        );
+        assert!(matches!(
+            chooser.choose(&"".into(), &items, 0),
+            Err(PromptError::NoAnswerLeftError(_, _))
+        ));
+    }
+
+    #[test]
+    fn default_chooser_test() {
+        let items = vec![String::from("a"), String::from("b"), String::from("c")];
+
+        assert_eq!(DefaultChooser.choose(&"".into(), &items, 2).unwrap(), 2);
+        assert_eq!(
+            DefaultChooser
+                .choose_multiple(&"".into(), &items, &vec![true, false, true])
+                .unwrap(),
+            vec![0, 2]
+        );
+    }
+}
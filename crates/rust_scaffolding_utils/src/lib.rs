@@ -26,24 +26,7 @@ pub fn add_member_to_workspace(
 ) -> Result<String, Error> {
     let mut workspace_cargo_toml: toml::Value = toml::from_str(cargo_toml.1.as_str())?;
 
-    let workspace_table = workspace_cargo_toml
-        .as_table_mut()
-        .ok_or(Error::MalformedCargoTomlError(
-            cargo_toml.0.clone(),
-            String::from("file does not conform to toml"),
-        ))?
-        .get_mut("workspace")
-        .ok_or(Error::MalformedCargoTomlError(
-            cargo_toml.0.clone(),
-            String::from("no workspace table found in workspace root"),
-        ))?
-        .as_table_mut()
-        .ok_or(Error::MalformedCargoTomlError(
-            cargo_toml.0.clone(),
-            String::from("workspace key is not a table"),
-        ))?;
-
-    let members = workspace_table
+    let members = workspace_table_mut(&mut workspace_cargo_toml, &cargo_toml.0)?
         .get_mut("members")
         .ok_or(Error::MalformedCargoTomlError(
             cargo_toml.0.clone(),
@@ -62,6 +45,58 @@ pub fn add_member_to_workspace(
     Ok(cargo_toml_str)
 }
 
+/// Adds the given dependency with the given version to the `[workspace.dependencies]` table,
+/// creating the table if it doesn't exist
+///
+/// Leaves the Cargo.toml untouched if the workspace already declares the dependency
+pub fn add_workspace_dependency(
+    cargo_toml: &(PathBuf, String),
+    dependency: String,
+    version: String,
+) -> Result<String, Error> {
+    let mut workspace_cargo_toml: toml::Value = toml::from_str(cargo_toml.1.as_str())?;
+
+    let dependencies = workspace_table_mut(&mut workspace_cargo_toml, &cargo_toml.0)?
+        .entry("dependencies")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or(Error::MalformedCargoTomlError(
+            cargo_toml.0.clone(),
+            String::from("the dependencies field in the workspace table should be a table"),
+        ))?;
+
+    if dependencies.contains_key(&dependency) {
+        return Ok(cargo_toml.1.clone());
+    }
+    dependencies.insert(dependency, toml::Value::String(version));
+
+    let cargo_toml_str = toml::to_string(&workspace_cargo_toml)?;
+
+    Ok(cargo_toml_str)
+}
+
+fn workspace_table_mut<'a>(
+    workspace_cargo_toml: &'a mut toml::Value,
+    cargo_toml_path: &PathBuf,
+) -> Result<&'a mut toml::Table, Error> {
+    workspace_cargo_toml
+        .as_table_mut()
+        .ok_or(Error::MalformedCargoTomlError(
+            cargo_toml_path.clone(),
+            String::from("file does not conform to toml"),
+        ))?
+        .get_mut("workspace")
+        .ok_or(Error::MalformedCargoTomlError(
+            cargo_toml_path.clone(),
+            String::from("no workspace table found in workspace root"),
+        ))?
+        .as_table_mut()
+        .ok_or(Error::MalformedCargoTomlError(
+            cargo_toml_path.clone(),
+            String::from("workspace key is not a table"),
+        ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +119,38 @@ resolver = "2"
 "#
         );
     }
+
+    #[test]
+    fn add_workspace_dependency_test() {
+        let cargo_toml = r#"[workspace]
+members = ["crates/*"]
+resolver = "2"
+"#;
+        let cargo_toml = add_workspace_dependency(
+            &(PathBuf::from("/"), cargo_toml.to_string()),
+            String::from("hdk"),
+            String::from("0.3.2"),
+        )
+        .unwrap();
+        assert_eq!(
+            cargo_toml,
+            r#"[workspace]
+members = ["crates/*"]
+resolver = "2"
+
+[workspace.dependencies]
+hdk = "0.3.2"
+"#
+        );
+
+        assert_eq!(
+            add_workspace_dependency(
+                &(PathBuf::from("/"), cargo_toml.clone()),
+                String::from("hdk"),
+                String::from("0.4.0"),
+            )
+            .unwrap(),
+            cargo_toml
+        );
+    }
 }
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use prompt_utils::build_chooser;
+use scaffold_happ::scaffold_happ;
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+/// Scaffolds a nixified hApp bundling the existing nixified DNAs.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name of the hApp that's being scaffolded
+    happ_name: String,
+
+    /// Names of the DNAs to include in the hApp, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    dnas: Option<Vec<String>>,
+
+    /// Folder in which to create the happ.yaml and the happ.nix files
+    #[arg(long, default_value = "happ")]
+    happ_folder: PathBuf,
+
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[arg(long)]
+    answers_file: Option<PathBuf>,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
+
+    let file_tree = scaffold_happ(
+        file_tree,
+        args.happ_name.clone(),
+        args.happ_folder,
+        args.dnas,
+        chooser.as_ref(),
+    )?;
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!("Successfully scaffolded hApp {}", args.happ_name.bold()).green()
+    );
+
+    Ok(())
+}
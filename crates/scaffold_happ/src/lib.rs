@@ -0,0 +1,378 @@
+use file_tree_utils::{create_dir_all, file_exists, insert_file, FileTree, FileTreeError};
+use holochain_scaffolding_utils::{find_nixified_dnas, NixifiedDna};
+use nix_scaffolding_utils::{add_flake_import, NixScaffoldingUtilsError};
+use prompt_utils::{Chooser, PromptError};
+use std::path::{Component, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ScaffoldHappError {
+    #[error(transparent)]
+    NixScaffoldingUtilsError(#[from] NixScaffoldingUtilsError),
+
+    #[error(transparent)]
+    HolochainScaffoldingUtilsError(#[from] holochain_scaffolding_utils::Error),
+
+    #[error(transparent)]
+    PromptError(#[from] PromptError),
+
+    #[error(transparent)]
+    FileTreeError(#[from] FileTreeError),
+
+    #[error("No nixified DNAs were found in this project.")]
+    NoDnasFoundError,
+
+    #[error("No DNAs were selected to be included in the hApp.")]
+    NoDnasSelectedError,
+
+    #[error("The dna {0} was not found in this project.")]
+    DnaNotFoundError(String),
+
+    #[error("A hApp already exists in the folder {0:?}.")]
+    HappAlreadyExistsError(PathBuf),
+
+    #[error("The path {0:?} is not valid UTF-8, so it can't be imported in the flake.nix.")]
+    NonUtf8PathError(PathBuf),
+}
+
+/// Creates a happ.yaml and a happ.nix in the given folder, bundling the given DNAs,
+/// and adds the new happ.nix to the imports of the flake.nix
+pub fn scaffold_happ(
+    mut file_tree: FileTree,
+    happ_name: String,
+    happ_folder: PathBuf,
+    dnas_to_include: Option<Vec<String>>,
+    chooser: &dyn Chooser,
+) -> Result<FileTree, ScaffoldHappError> {
+    let happ_folder: PathBuf = happ_folder
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+    let happ_manifest_path = happ_folder.join("happ.yaml");
+    let happ_nix_path = happ_folder.join("happ.nix");
+
+    if file_exists(&file_tree, &happ_manifest_path) || file_exists(&file_tree, &happ_nix_path) {
+        return Err(ScaffoldHappError::HappAlreadyExistsError(happ_folder));
+    }
+    let happ_nix_import = happ_nix_path
+        .to_str()
+        .map(|path| format!("./{path}"))
+        .ok_or_else(|| ScaffoldHappError::NonUtf8PathError(happ_nix_path.clone()))?;
+
+    let dnas = get_or_choose_dnas(&file_tree, &happ_name, dnas_to_include, chooser)?;
+
+    create_dir_all(&mut file_tree, &happ_folder)?;
+    insert_file(
+        &mut file_tree,
+        &happ_manifest_path,
+        &happ_manifest(&happ_name, &dnas),
+    )?;
+    insert_file(&mut file_tree, &happ_nix_path, &happ_nix(&happ_name, &dnas))?;
+
+    eprintln!("Added the hApp {happ_name} to {happ_folder:?}.");
+
+    let file_tree = add_flake_import(file_tree, happ_nix_import)?;
+
+    Ok(file_tree)
+}
+
+fn get_or_choose_dnas(
+    file_tree: &FileTree,
+    happ_name: &String,
+    dnas_to_include: Option<Vec<String>>,
+    chooser: &dyn Chooser,
+) -> Result<Vec<NixifiedDna>, ScaffoldHappError> {
+    let nixified_dnas = find_nixified_dnas(file_tree)?;
+
+    if nixified_dnas.is_empty() {
+        return Err(ScaffoldHappError::NoDnasFoundError);
+    }
+
+    if let Some(dna_names) = dnas_to_include {
+        return dna_names
+            .into_iter()
+            .map(|dna_name| {
+                nixified_dnas
+                    .iter()
+                    .find(|dna| dna.name.eq(&dna_name))
+                    .cloned()
+                    .ok_or(ScaffoldHappError::DnaNotFoundError(dna_name))
+            })
+            .collect();
+    }
+
+    if nixified_dnas.len() == 1 {
+        return Ok(nixified_dnas);
+    }
+
+    let dna_names: Vec<String> = nixified_dnas.iter().map(|dna| dna.name.clone()).collect();
+
+    let selection = chooser.choose_multiple(
+        &format!(
+            "Multiple DNAs were found in this repository, choose the ones to include in the {happ_name} hApp:"
+        ),
+        &dna_names,
+        &vec![true; dna_names.len()],
+    )?;
+
+    if selection.is_empty() {
+        return Err(ScaffoldHappError::NoDnasSelectedError);
+    }
+
+    Ok(selection
+        .into_iter()
+        .map(|index| nixified_dnas[index].clone())
+        .collect())
+}
+
+fn happ_manifest(happ_name: &String, dnas: &Vec<NixifiedDna>) -> String {
+    let roles: Vec<String> = dnas
+        .iter()
+        .map(|dna| {
+            format!(
+                r#"  - name: {}
+    provisioning:
+      strategy: create
+      deferred: false
+    dna:
+      modifiers:
+        network_seed: ~
+        properties: ~
+        origin_time: ~
+        quantum_time: ~
+      installed_hash: ~
+      clone_limit: 0
+"#,
+                dna.name
+            )
+        })
+        .collect();
+
+    format!(
+        r#"---
+manifest_version: "1"
+name: {happ_name}
+description: ~
+roles:
+{}"#,
+        roles.join("")
+    )
+}
+
+fn happ_nix(happ_name: &String, dnas: &Vec<NixifiedDna>) -> String {
+    let dnas: Vec<String> = dnas
+        .iter()
+        .map(|dna| {
+            format!(
+                "          {} = self'.packages.{};\n",
+                dna.name, dna.package_name
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{ inputs, ... }}:
+
+{{
+  perSystem = {{ inputs', config, pkgs, system, lib, self', options, ... }}: {{
+    packages = {{
+      {happ_name} = inputs.hc-infra.outputs.builders.${{system}}.happ {{
+        happManifest = ./happ.yaml;
+        dnas = {{
+{}        }};
+      }};
+    }};
+  }};
+}}
+"#,
+        dnas.join("")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use build_fs_tree::{dir, file};
+    use file_tree_utils::file_content;
+    use holochain_scaffolding_utils::test_utils::{empty_dna_nix, empty_dna_yaml};
+    use pretty_assertions::assert_eq;
+    use prompt_utils::{DialoguerChooser, ScriptedChooser};
+
+    #[test]
+    fn scaffold_happ_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+        };
+
+        let repo = scaffold_happ(
+            repo,
+            "myhapp".into(),
+            PathBuf::from("./happ"),
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("happ/happ.nix").as_path()).unwrap(),
+            r#"{ inputs, ... }:
+
+{
+  perSystem = { inputs', config, pkgs, system, lib, self', options, ... }: {
+    packages = {
+      myhapp = inputs.hc-infra.outputs.builders.${system}.happ {
+        happManifest = ./happ.yaml;
+        dnas = {
+          mydna = self'.packages.my_dna;
+        };
+      };
+    };
+  };
+}
+"#
+        );
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("happ/happ.yaml").as_path()).unwrap(),
+            r#"---
+manifest_version: "1"
+name: myhapp
+description: ~
+roles:
+  - name: mydna
+    provisioning:
+      strategy: create
+      deferred: false
+    dna:
+      modifiers:
+        network_seed: ~
+        properties: ~
+        origin_time: ~
+        quantum_time: ~
+      installed_hash: ~
+      clone_limit: 0
+"#
+        );
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  inputs = {
+    holonix.url = "github:holochain/holonix";
+    hc-infra.url = "github:holochain-open-dev/utils";
+  };
+
+  outputs = inputs @ { ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake
+    {
+      inherit inputs;
+    }
+    {
+      imports = [
+        ./happ/happ.nix
+        ./dna.nix
+      ];
+    };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn scaffold_happ_with_unknown_dna_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+        };
+
+        let result = scaffold_happ(
+            repo,
+            "myhapp".into(),
+            PathBuf::from("happ"),
+            Some(vec!["otherdna".into()]),
+            &DialoguerChooser::new(true),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ScaffoldHappError::DnaNotFoundError(dna)) if dna == "otherdna"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scaffold_happ_in_non_utf8_folder_test() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+        };
+
+        let result = scaffold_happ(
+            repo,
+            "myhapp".into(),
+            PathBuf::from(OsStr::from_bytes(b"happ\xff")),
+            Some(vec!["mydna".into()]),
+            &DialoguerChooser::new(true),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ScaffoldHappError::NonUtf8PathError(_))
+        ));
+    }
+
+    #[test]
+    fn scaffold_happ_with_scripted_choice_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "other" => dir! {
+                "dna.nix" => file!(empty_dna_nix().replace("my_dna", "other_dna")),
+                "dna.yaml" => file!(empty_dna_yaml("otherdna")),
+            }
+        };
+
+        let repo = scaffold_happ(
+            repo,
+            "myhapp".into(),
+            PathBuf::from("happ"),
+            None,
+            &ScriptedChooser::new(vec!["otherdna".into()]),
+        )
+        .unwrap();
+
+        let happ_nix = file_content(&repo, PathBuf::from("happ/happ.nix").as_path()).unwrap();
+        assert!(happ_nix.contains("otherdna = self'.packages.other_dna;"));
+        assert!(!happ_nix.contains("mydna"));
+    }
+
+    fn default_flake_nix() -> String {
+        String::from(
+            r#"{
+  inputs = {
+    holonix.url = "github:holochain/holonix";
+    hc-infra.url = "github:holochain-open-dev/utils";
+  };
+
+  outputs = inputs @ { ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake
+    {
+      inherit inputs;
+    }
+    {
+      imports = [
+        ./dna.nix
+      ];
+    };
+}
+"#,
+        )
+    }
+}
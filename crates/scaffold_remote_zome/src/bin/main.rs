@@ -1,14 +1,19 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use build_fs_tree::{Build, MergeableFileSystemTree};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use colored::Colorize;
-use scaffold_remote_zome::scaffold_remote_zome;
+use npm_scaffolding_utils::guess_or_choose_package_manager_for_package;
+use prompt_utils::build_chooser;
+use scaffold_remote_zome::{
+    post_scaffold_hooks, read_remote_zome_manifest, run_post_scaffold_hooks, scaffold_remote_zome,
+    RemoteSource, RemoteZomeManifest, RemoteZomePair, ScaffoldRemoteZomeReport,
+};
+use serde::Serialize;
 use std::{
     ffi::OsString,
-    path::PathBuf,
-    process::{Command, ExitCode},
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
-use sync_npm_git_dependencies_with_nix::synchronize_npm_git_dependencies_with_nix;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -16,7 +21,14 @@ struct Args {
     /// Name of the module zome that's being scaffolded
     module_name: String,
 
-    /// Name of the integrity zome that's being scaffolded
+    /// Path to the module.yaml manifest of the zome that's being scaffolded, or to a local checkout
+    /// of its repository containing one. Defaults to the one in --local-path, if given.
+    /// The other flags describing the zome override the values from the manifest.
+    #[arg(long)]
+    module_manifest: Option<PathBuf>,
+
+    /// Name of the integrity zome that's being scaffolded.
+    /// Together with --coordinator-zome-name, it replaces the zome pairs from the module manifest.
     #[arg(long)]
     integrity_zome_name: Option<String>,
 
@@ -24,35 +36,89 @@ struct Args {
     #[arg(long)]
     coordinator_zome_name: Option<String>,
 
-    /// URL for the git repository of the zome that's being scaffolded
-    #[arg(long)]
-    remote_zome_git_url: String,
+    /// URL for the git repository of the zome that's being scaffolded.
+    /// Supports github:owner/repo, gitlab:owner/repo, git+https://, git+ssh:// and path: urls.
+    #[arg(long, required_unless_present = "local_path")]
+    remote_zome_git_url: Option<String>,
 
-    /// Branch for the git repository of the zome that's being scaffolded
+    /// Branch for the git repository of the zome that's being scaffolded.
+    /// Takes precedence over the branch included in --remote-zome-git-url, if any.
     #[arg(long)]
     remote_zome_git_branch: Option<String>,
 
+    /// Path to a local checkout of the repository of the zome that's being scaffolded, relative to --path,
+    /// to develop the zome side by side with this repository.
+    /// It's added as a path: flake input and a file: NPM dependency instead of git sources;
+    /// use switch-remote-zome-source to go back to the git repository.
+    #[arg(long, conflicts_with_all = ["remote_zome_git_url", "remote_zome_git_branch"])]
+    local_path: Option<PathBuf>,
+
     /// Name of the UI package for the zome that's being scaffolded
     #[arg(long)]
-    remote_npm_package_name: String,
+    remote_npm_package_name: Option<String>,
 
     /// Internal path of the UI package
     #[arg(long)]
-    remote_npm_package_path: PathBuf,
+    remote_npm_package_path: Option<PathBuf>,
 
-    /// DNA of the local repository in which the zome should be scaffolded
-    #[arg(long)]
-    local_dna_to_add_the_zome_to: Option<String>,
+    /// DNAs of the local repository in which each of the zome pairs should be scaffolded, separated by commas,
+    /// in the same order as the zome pairs in the module manifest
+    #[arg(
+        long,
+        value_delimiter = ',',
+        visible_alias = "local-dna-to-add-the-zome-to"
+    )]
+    local_dnas_to_add_the_zomes_to: Vec<String>,
 
     /// NPM package for the local repository in which the UI for the zome should be scaffolded
     #[arg(long)]
     local_npm_package_to_add_the_ui_to: Option<String>,
 
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[arg(long)]
+    answers_file: Option<PathBuf>,
+
+    /// Don't run any command after the files have been written: updating flake.lock,
+    /// synchronizing the NPM git dependencies with it and installing them are left to you.
+    #[arg(long)]
+    skip_post_scaffold_hooks: bool,
+
+    /// Only update the flake input of the module with `nix flake lock --update-input`,
+    /// instead of updating all the flake inputs with `nix flake update`.
+    #[arg(long, conflicts_with = "skip_post_scaffold_hooks")]
+    update_input_only: bool,
+
+    /// Format of the output. Progress messages are always printed to stderr.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
     /// The path of the file tree to modify.
     #[clap(long, default_value = "./.")]
     pub path: PathBuf,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    /// A success message
+    Text,
+    /// A JSON report of the changes made and the commands run, for tools that wrap this one
+    Json,
+}
+
+/// Report printed with `--output json`
+#[derive(Serialize, Debug)]
+struct JsonOutput {
+    #[serde(flatten)]
+    report: ScaffoldRemoteZomeReport,
+    /// Commands run after the files were written
+    post_scaffold_hooks: Vec<String>,
+}
+
 fn main() -> ExitCode {
     if let Err(err) = internal_main() {
         eprintln!("{}", format!("Error: {err:?}").red());
@@ -64,39 +130,121 @@ fn main() -> ExitCode {
 fn internal_main() -> Result<()> {
     let args = Args::parse();
 
+    let remote_zome_manifest = remote_zome_manifest(&args)?;
+    let remote_source = match (&args.local_path, &args.remote_zome_git_url) {
+        (Some(local_path), _) => RemoteSource::Path {
+            path: local_path.clone(),
+        },
+        (None, Some(remote_zome_git_url)) => {
+            RemoteSource::parse(remote_zome_git_url, args.remote_zome_git_branch.clone())?
+        }
+        (None, None) => Err(anyhow!(
+            "Either --remote-zome-git-url or --local-path needs to be provided"
+        ))?,
+    };
+    let local_dnas_to_add_the_zomes_to = args
+        .local_dnas_to_add_the_zomes_to
+        .iter()
+        .map(|dna| Some(dna.clone()))
+        .collect();
+
     let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
 
-    let file_tree = scaffold_remote_zome(
+    let (file_tree, report) = scaffold_remote_zome(
         file_tree,
         args.module_name.clone(),
-        args.integrity_zome_name.clone(),
-        args.coordinator_zome_name.clone(),
-        args.remote_zome_git_url,
-        args.remote_zome_git_branch,
-        args.remote_npm_package_name,
-        args.remote_npm_package_path,
-        args.local_dna_to_add_the_zome_to,
+        remote_zome_manifest,
+        remote_source,
+        local_dnas_to_add_the_zomes_to,
         args.local_npm_package_to_add_the_ui_to,
+        chooser.as_ref(),
     )?;
 
+    let hooks = match args.skip_post_scaffold_hooks {
+        true => vec![],
+        false => post_scaffold_hooks(
+            &args.module_name,
+            args.update_input_only,
+            guess_or_choose_package_manager_for_package(
+                &file_tree,
+                &args.path,
+                report
+                    .npm_dependency
+                    .package_json
+                    .parent()
+                    .unwrap_or(Path::new("")),
+                chooser.as_ref(),
+            )?,
+        ),
+    };
+
     let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
 
     file_tree.build(&args.path)?;
 
-    // Run nix flake update
-
-    // Run nix develop -c bash "pnpm install"
-
-    println!(
-        "{}",
-        format!("Successfully scaffolded zome {}", args.module_name.bold()).green()
-    );
+    if args.output == OutputFormat::Text {
+        println!(
+            "{}",
+            format!("Successfully scaffolded zome {}", args.module_name.bold()).green()
+        );
+    }
 
-    println!("Running nix flake update...");
-    println!("");
-    Command::new("nix").args(["flake", "update"]).output()?;
+    run_post_scaffold_hooks(&hooks, &args.path)?;
 
-    synchronize_npm_git_dependencies_with_nix()?;
+    if args.output == OutputFormat::Json {
+        let output = JsonOutput {
+            report,
+            post_scaffold_hooks: hooks.iter().map(|hook| hook.description()).collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
 
     Ok(())
 }
+
+/// Builds the manifest for the zome from the given module manifest, overriding its values with the given flags
+fn remote_zome_manifest(args: &Args) -> Result<RemoteZomeManifest> {
+    let local_checkout = args
+        .local_path
+        .as_ref()
+        .map(|local_path| args.path.join(local_path));
+    let manifest = match args.module_manifest.clone().or(local_checkout) {
+        Some(module_manifest) => Some(read_remote_zome_manifest(&module_manifest)?),
+        None => None,
+    };
+
+    let npm_package_name = args
+        .remote_npm_package_name
+        .clone()
+        .or(manifest.as_ref().map(|m| m.npm_package_name.clone()))
+        .ok_or(anyhow!(
+            "Either --module-manifest or --remote-npm-package-name needs to be provided"
+        ))?;
+    let npm_package_path = args
+        .remote_npm_package_path
+        .clone()
+        .or(manifest.as_ref().map(|m| m.npm_package_path.clone()))
+        .ok_or(anyhow!(
+            "Either --module-manifest or --remote-npm-package-path needs to be provided"
+        ))?;
+
+    let zomes = match (&args.integrity_zome_name, &args.coordinator_zome_name) {
+        (None, None) => manifest
+            .as_ref()
+            .map(|m| m.zomes.clone())
+            .ok_or(anyhow!(
+                "Either --module-manifest or --integrity-zome-name and --coordinator-zome-name need to be provided"
+            ))?,
+        (integrity_zome_name, coordinator_zome_name) => vec![RemoteZomePair {
+            integrity_zome_name: integrity_zome_name.clone(),
+            coordinator_zome_name: coordinator_zome_name.clone(),
+        }],
+    };
+
+    Ok(RemoteZomeManifest {
+        zomes,
+        npm_package_name,
+        npm_package_path,
+    })
+}
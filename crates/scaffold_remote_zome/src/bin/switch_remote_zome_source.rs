@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use npm_scaffolding_utils::guess_or_choose_package_manager_for_package;
+use prompt_utils::build_chooser;
+use scaffold_remote_zome::{
+    post_scaffold_hooks, run_post_scaffold_hooks, switch_remote_zome_source, RemoteSource,
+};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Switches a zome previously scaffolded with scaffold-remote-zome between a local checkout and its git repository.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name of the module zome that's being switched
+    module_name: String,
+
+    /// URL for the git repository to switch the zome to, for example to release after developing it locally
+    #[arg(long, required_unless_present = "local_path")]
+    remote_zome_git_url: Option<String>,
+
+    /// Branch for the git repository to switch the zome to
+    #[arg(long)]
+    remote_zome_git_branch: Option<String>,
+
+    /// Path to a local checkout of the repository to switch the zome to, relative to --path
+    #[arg(long, conflicts_with_all = ["remote_zome_git_url", "remote_zome_git_branch"])]
+    local_path: Option<PathBuf>,
+
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[arg(long)]
+    answers_file: Option<PathBuf>,
+
+    /// Don't run any command after the files have been written: updating flake.lock,
+    /// synchronizing the NPM git dependencies with it and installing them are left to you.
+    #[arg(long)]
+    skip_post_scaffold_hooks: bool,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let remote_source = match (args.local_path, args.remote_zome_git_url) {
+        (Some(local_path), _) => RemoteSource::Path { path: local_path },
+        (None, Some(remote_zome_git_url)) => {
+            RemoteSource::parse(&remote_zome_git_url, args.remote_zome_git_branch)?
+        }
+        (None, None) => Err(anyhow!(
+            "Either --remote-zome-git-url or --local-path needs to be provided"
+        ))?,
+    };
+    let flake_ref = remote_source.flake_ref();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
+
+    let (file_tree, switched_package_jsons) =
+        switch_remote_zome_source(file_tree, args.module_name.clone(), remote_source)?;
+
+    let hooks = match args.skip_post_scaffold_hooks {
+        true => vec![],
+        false => post_scaffold_hooks(
+            &args.module_name,
+            true,
+            guess_or_choose_package_manager_for_package(
+                &file_tree,
+                &args.path,
+                switched_package_jsons
+                    .first()
+                    .and_then(|package_json| package_json.parent())
+                    .unwrap_or(Path::new("")),
+                chooser.as_ref(),
+            )?,
+        ),
+    };
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!(
+            "Successfully switched zome {} to {}",
+            args.module_name.bold(),
+            flake_ref.bold()
+        )
+        .green()
+    );
+
+    run_post_scaffold_hooks(&hooks, &args.path)?;
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use scaffold_remote_zome::unscaffold_remote_zome;
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+/// Removes a zome previously scaffolded with scaffold-remote-zome.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name of the module zome that's being removed
+    module_name: String,
+
+    /// Name of the UI package for the zome that's being removed
+    #[arg(long)]
+    remote_npm_package_name: String,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+
+    let file_tree = unscaffold_remote_zome(
+        file_tree,
+        args.module_name.clone(),
+        args.remote_npm_package_name,
+    )?;
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!("Successfully removed zome {}", args.module_name.bold()).green()
+    );
+
+    Ok(())
+}
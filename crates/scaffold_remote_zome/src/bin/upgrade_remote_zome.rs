@@ -0,0 +1,98 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use npm_scaffolding_utils::guess_or_choose_package_manager_for_package;
+use prompt_utils::build_chooser;
+use scaffold_remote_zome::{post_scaffold_hooks, run_post_scaffold_hooks, upgrade_remote_zome};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Switches a zome previously scaffolded with scaffold-remote-zome to a new branch or revision.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name of the module zome that's being upgraded
+    module_name: String,
+
+    /// New branch or revision for the git repository of the zome that's being upgraded
+    #[arg(long)]
+    remote_zome_git_branch: String,
+
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[arg(long)]
+    answers_file: Option<PathBuf>,
+
+    /// Don't run any command after the files have been written: updating flake.lock,
+    /// synchronizing the NPM git dependencies with it and installing them are left to you.
+    #[arg(long)]
+    skip_post_scaffold_hooks: bool,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
+
+    let (file_tree, switched_package_jsons) = upgrade_remote_zome(
+        file_tree,
+        args.module_name.clone(),
+        args.remote_zome_git_branch.clone(),
+    )?;
+
+    let hooks = match args.skip_post_scaffold_hooks {
+        true => vec![],
+        false => post_scaffold_hooks(
+            &args.module_name,
+            true,
+            guess_or_choose_package_manager_for_package(
+                &file_tree,
+                &args.path,
+                switched_package_jsons
+                    .first()
+                    .and_then(|package_json| package_json.parent())
+                    .unwrap_or(Path::new("")),
+                chooser.as_ref(),
+            )?,
+        ),
+    };
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!(
+            "Successfully upgraded zome {} to {}",
+            args.module_name.bold(),
+            args.remote_zome_git_branch.bold()
+        )
+        .green()
+    );
+
+    run_post_scaffold_hooks(&hooks, &args.path)?;
+
+    Ok(())
+}
@@ -1,18 +1,33 @@
-use nix_scaffolding_utils::{add_flake_input, NixScaffoldingUtilsError};
-use npm_scaffolding_utils::{add_npm_dependency, NpmScaffoldingUtilsError};
+use nix_scaffolding_utils::{
+    add_flake_input, get_flake_input_url, remove_flake_input, set_flake_input_url,
+    NixScaffoldingUtilsError,
+};
+use npm_scaffolding_utils::{
+    add_npm_dependency_to_package, find_packages_with_npm_dependency,
+    get_or_choose_package_json_for_dependency, remove_npm_dependency,
+    update_npm_dependency_source_in_package, NpmScaffoldingUtilsError, NPM_DEPENDENCY_SECTIONS,
+};
 use anyhow::Result;
-use dialoguer::{theme::ColorfulTheme, Select};
 use file_tree_utils::{
-    find_files_by_extension, find_files_by_name, insert_file, map_file, FileTree, FileTreeError,
+    file_content, find_files_by_name, flatten_file_tree, map_file, FileTree, FileTreeError,
+};
+use prompt_utils::{Chooser, PromptError};
+use holochain_scaffolding_utils::{
+    add_zome_to_nixified_dna, find_nixified_dnas, find_zomes_in_nixified_dna,
+    get_or_choose_nixified_dna, remove_zomes_from_nixified_dna, NixifiedDna,
 };
-use holochain_types::prelude::{
-    DnaManifest, DnaManifestCurrentBuilder, ZomeDependency, ZomeLocation,
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
 };
-use path_clean::PathClean;
-use regex::{Captures, Regex};
-use std::path::PathBuf;
 use thiserror::Error;
 
+mod post_scaffold_hooks;
+mod remote_source;
+pub use post_scaffold_hooks::{post_scaffold_hooks, run_post_scaffold_hooks, PostScaffoldHook};
+pub use remote_source::RemoteSource;
+
 #[derive(Error, Debug)]
 pub enum ScaffoldRemoteZomeError {
     #[error(transparent)]
@@ -21,323 +36,378 @@ pub enum ScaffoldRemoteZomeError {
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 
-    #[error(transparent)]
-    RegexError(#[from] regex::Error),
-
     #[error(transparent)]
     NixScaffoldingUtilsError(#[from] NixScaffoldingUtilsError),
 
     #[error(transparent)]
-    DialoguerError(#[from] dialoguer::Error),
+    PromptError(#[from] PromptError),
 
     #[error(transparent)]
     FileTreeError(#[from] FileTreeError),
 
-    #[error("No nixified DNAs were found in this project.")]
-    NoDnasFoundError,
-
-    #[error("The dna {0} was not found in this project.")]
-    DnaNotFoundError(String),
-
-    #[error("No \"zomes = {{\" code property was found in the nix file {0:?}.")]
-    ZomesPropertyNotFoundError(PathBuf),
-
-    #[error("No integrity or coordinator zomes were specified to be scaffolded.")]
-    NoZomesSpecifiedError,
+    #[error(transparent)]
+    HolochainScaffoldingUtilsError(#[from] holochain_scaffolding_utils::Error),
 
     #[error(transparent)]
     SerdeYamlError(#[from] serde_yaml::Error),
 
-    #[error("Malformed DNA manifest at {0}: {1}")]
-    MalformedDnaManifest(PathBuf, String),
-}
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
 
-pub fn scaffold_remote_zome(
-    file_tree: FileTree,
-    module_name: String,
-    integrity_zome_name: Option<String>,
-    coordinator_zome_name: Option<String>,
-    remote_zome_git_url: String,
-    remote_zome_git_branch: Option<String>,
-    remote_npm_package_name: String,
-    remote_npm_package_path: PathBuf,
-    local_dna_to_add_the_zome_to: Option<String>,
-    local_npm_package_to_add_the_ui_to: Option<String>,
-) -> Result<FileTree, ScaffoldRemoteZomeError> {
-    let nix_git_url = format!(
-        "{remote_zome_git_url}{}",
-        remote_zome_git_branch
-            .clone()
-            .map(|b| format!("/{b}"))
-            .unwrap_or_default()
-    );
-    let mut file_tree = add_flake_input(file_tree, module_name.clone(), nix_git_url.clone())?;
+    #[error(transparent)]
+    SynchronizeNpmGitDependenciesWithNixError(
+        #[from] sync_npm_git_dependencies_with_nix::SynchronizeNpmGitDependenciesWithNixError,
+    ),
 
-    let dna = get_or_choose_dna(&file_tree, &module_name, local_dna_to_add_the_zome_to)?;
+    #[error("The module manifest {0:?} could not be read: {1}")]
+    RemoteZomeManifestNotFoundError(PathBuf, std::io::Error),
 
-    add_zome_to_nixified_dna(
-        &mut file_tree,
-        dna,
-        &module_name,
-        integrity_zome_name,
-        coordinator_zome_name,
-    )?;
+    #[error("The zome {0} already exists in the DNA {1}: was the module already scaffolded?")]
+    ZomeAlreadyExistsError(String, String),
 
-    let npm_dependency_source = format!(
-        "{remote_zome_git_url}{}&path:{}",
-        remote_zome_git_branch
-            .map(|b| format!("#{b}"))
-            .unwrap_or_default(),
-        remote_npm_package_path.to_str().unwrap()
-    );
+    #[error("The flake input for the module {0} was not found in flake.nix.")]
+    ModuleNotFoundError(String),
 
-    let m = module_name.clone();
+    #[error("Unsupported flake input url {0}: only git repositories can be upgraded.")]
+    UnsupportedFlakeInputUrlError(String),
 
-    let clo = move |packages| select_npm_package(m.clone(), packages);
+    #[error("Invalid remote source {0}: it must be a github:, gitlab:, git+https:, git+ssh: or path: url.")]
+    InvalidRemoteSourceError(String),
 
-    let file_tree = add_npm_dependency(file_tree, remote_npm_package_name, npm_dependency_source, local_npm_package_to_add_the_ui_to, Some(Box::new(clo)))?;
+    #[error("The path {0:?} is not valid UTF-8, so it can't be used in an NPM dependency source.")]
+    NonUtf8PathError(PathBuf),
 
-    Ok(file_tree)
+    #[error("The post scaffold command \"{0}\" failed: {1}")]
+    PostScaffoldHookError(String, String),
 }
 
-fn select_npm_package(module_name: String, npm_packages: Vec<String>) -> Result<usize, NpmScaffoldingUtilsError> {
-    let mut i = 0;
-    let mut found = false;
+/// Name of the file in which module repositories describe their zomes and their UI package
+pub const REMOTE_ZOME_MANIFEST_FILE_NAME: &str = "module.yaml";
+
+/// Describes what `scaffold_remote_zome` needs to add a module to a project,
+/// so that users only need to pass the URL of the module
+///
+/// Module repositories ship it as a `module.yaml` at their root
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteZomeManifest {
+    /// Integrity and coordinator zome pairs of the module, each of which can be added to a different DNA
+    pub zomes: Vec<RemoteZomePair>,
+    /// Name of the UI package of the module
+    pub npm_package_name: String,
+    /// Path of the UI package inside the module repository
+    pub npm_package_path: PathBuf,
+}
 
-    while !found {
-        let package = &npm_packages[i];
-        if !(package.ends_with("-dev") || package.contains("test")) {
-            found = true;
-        } else {
-            i += 1;
-        }
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteZomePair {
+    pub integrity_zome_name: Option<String>,
+    pub coordinator_zome_name: Option<String>,
+}
+
+impl RemoteZomePair {
+    /// Name to refer to this zome pair by: the coordinator zome name if there is one
+    pub fn name(&self) -> String {
+        self.coordinator_zome_name
+            .clone()
+            .or(self.integrity_zome_name.clone())
+            .unwrap_or_default()
     }
+}
 
-    let default = i;
-    
-    Ok(Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(
-        format!(    
-"Multiple NPM packages were found in this project, choose one to add the UI package for the {module_name} zome:"))
-        .default(default)
-        .items(&npm_packages[..])
-        .interact()?)
+/// What `scaffold_remote_zome` changed in the project, for the tools that wrap it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScaffoldRemoteZomeReport {
+    pub flake_input: FlakeInputReport,
+    /// Zomes added to the DNAs, one entry per zome pair of the module
+    pub zomes: Vec<AddedZomesReport>,
+    pub npm_dependency: NpmDependencyReport,
+    /// Files created or modified, relative to the root of the project
+    pub modified_files: Vec<PathBuf>,
 }
 
-#[derive(Debug, Clone)]
-pub struct NixifiedDna {
-    name: String,
-    dna_nix: (PathBuf, String),
-    dna_manifest: (PathBuf, String),
-    dna_manifest_reference_index: usize,
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FlakeInputReport {
+    pub name: String,
+    pub url: String,
 }
 
-fn add_zome_to_nixified_dna(
-    file_tree: &mut FileTree,
-    nixified_dna: NixifiedDna,
-    module_name: &String,
-    integrity_zome_name: Option<String>,
-    coordinator_zome_name: Option<String>,
-) -> Result<(), ScaffoldRemoteZomeError> {
-    if integrity_zome_name.is_none() && coordinator_zome_name.is_none() {
-        return Err(ScaffoldRemoteZomeError::NoZomesSpecifiedError);
-    }
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AddedZomesReport {
+    pub dna: String,
+    pub dna_nix: PathBuf,
+    pub dna_manifest: PathBuf,
+    /// Integrity zome added to the DNA, if it was not already added for a previous zome pair
+    pub integrity_zome_name: Option<String>,
+    pub coordinator_zome_name: Option<String>,
+}
 
-    let integrity_string_to_add = integrity_zome_name
-        .clone()
-        .map(|name| format!("\n          {name} = inputs'.{module_name}.packages.{name};"))
-        .unwrap_or_default();
-    let coordinator_string_to_add = coordinator_zome_name
-        .clone()
-        .map(|name| format!("\n          {name} = inputs'.{module_name}.packages.{name};"))
-        .unwrap_or_default();
-    let string_to_add = format!("{integrity_string_to_add}{coordinator_string_to_add}");
-
-    map_file(
-        file_tree,
-        &nixified_dna.dna_nix.0.clone(),
-        |mut dna_contents| {
-            let zomes_re = Regex::new(r#"zomes = \{"#)?;
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NpmDependencyReport {
+    /// package.json of the package the dependency was added to
+    pub package_json: PathBuf,
+    pub name: String,
+    pub source: String,
+}
 
-            let captures_iter: Vec<Captures<'_>> = zomes_re.captures_iter(&dna_contents).collect();
+/// Reads the module manifest from the given file, or from the `module.yaml` at the root
+/// of the given local checkout of the module repository
+pub fn read_remote_zome_manifest(
+    path: &Path,
+) -> Result<RemoteZomeManifest, ScaffoldRemoteZomeError> {
+    let manifest_path = match path.is_dir() {
+        true => path.join(REMOTE_ZOME_MANIFEST_FILE_NAME),
+        false => path.to_path_buf(),
+    };
 
-            match captures_iter.len() {
-                0 => {
-                    return Err(ScaffoldRemoteZomeError::ZomesPropertyNotFoundError(
-                        nixified_dna.dna_nix.0.clone(),
-                    ));
-                }
-                1 => Ok(zomes_re
-                    .replace(&dna_contents, format!("zomes = {{{string_to_add}"))
-                    .to_string()),
-                _ => {
-                    let distance_to_dna_manifest = |captures: &Captures<'_>| {
-                        ((nixified_dna.dna_manifest_reference_index as isize)
-                            - captures.get(0).unwrap().start() as isize)
-                            .abs() as usize
-                    };
-                    let captures = captures_iter
-                        .into_iter()
-                        .min_by_key(distance_to_dna_manifest)
-                        .unwrap();
-
-                    dna_contents.insert_str(captures.get(0).unwrap().end(), &string_to_add);
-
-                    Ok(dna_contents)
-                }
-            }
-        },
-    )?;
+    let manifest_content = std::fs::read_to_string(&manifest_path).map_err(|err| {
+        ScaffoldRemoteZomeError::RemoteZomeManifestNotFoundError(manifest_path.clone(), err)
+    })?;
+
+    Ok(serde_yaml::from_str(&manifest_content)?)
+}
 
-    println!("Added the integrity zome {integrity_zome_name:?} and the coordinator zome {coordinator_zome_name:?} to {:?}.", nixified_dna.dna_nix.0);
+/// Adds the module as a flake input, each of its zome pairs to a nixified DNA and its UI package as an NPM dependency
+///
+/// `local_dnas_to_add_the_zomes_to` contains the DNA to add each of the zome pairs to, in the same order
+/// as the zome pairs of the manifest; the DNA is chosen for the zome pairs that don't have one
+pub fn scaffold_remote_zome(
+    file_tree: FileTree,
+    module_name: String,
+    remote_zome_manifest: RemoteZomeManifest,
+    remote_source: RemoteSource,
+    local_dnas_to_add_the_zomes_to: Vec<Option<String>>,
+    local_npm_package_to_add_the_ui_to: Option<String>,
+    chooser: &dyn Chooser,
+) -> Result<(FileTree, ScaffoldRemoteZomeReport), ScaffoldRemoteZomeError> {
+    let original_files = flatten_file_tree(&file_tree);
+
+    // Choose the DNAs for all the zome pairs and check that their zomes are not there yet before changing anything
+    // DNAs are identified by their nix file and their package, since different DNAs can have the same name
+    // The integrity zome that each coordinator zome depends on is kept apart, since it may have been added already
+    let mut zomes_to_add: Vec<(NixifiedDna, RemoteZomePair, Option<String>)> = vec![];
+    let mut zomes_added_to_dna: BTreeMap<(PathBuf, String), BTreeSet<String>> = BTreeMap::new();
+    for (i, zome_pair) in remote_zome_manifest.zomes.into_iter().enumerate() {
+        let zome_name = zome_pair.name();
+        let dna = get_or_choose_nixified_dna(
+            &file_tree,
+            local_dnas_to_add_the_zomes_to.get(i).cloned().flatten(),
+            &format!("Multiple DNAs were found in this repository, choose one to scaffold the {zome_name} zome of the {module_name} module into:"),
+            chooser,
+        )?;
+
+        let existing_zomes = find_zomes_in_nixified_dna(&dna)?;
+        let zomes_added = zomes_added_to_dna
+            .entry((dna.dna_nix.0.clone(), dna.package_name.clone()))
+            .or_default();
+
+        // Zome pairs of the same module can share their integrity zome, which only needs to be added once
+        let mut filter_zome = |zome_name: Option<String>| match zome_name {
+            Some(zome_name) if existing_zomes.contains(&zome_name) => Err(
+                ScaffoldRemoteZomeError::ZomeAlreadyExistsError(zome_name, dna.name.clone()),
+            ),
+            Some(zome_name) if !zomes_added.insert(zome_name.clone()) => Ok(None),
+            zome_name => Ok(zome_name),
+        };
+        let coordinator_dependency = zome_pair.integrity_zome_name.clone();
+        let zome_pair = RemoteZomePair {
+            integrity_zome_name: filter_zome(zome_pair.integrity_zome_name)?,
+            coordinator_zome_name: filter_zome(zome_pair.coordinator_zome_name)?,
+        };
 
-    let dna_manifest: DnaManifest = serde_yaml::from_str(nixified_dna.dna_manifest.1.as_str())?;
+        if zome_pair.integrity_zome_name.is_some() || zome_pair.coordinator_zome_name.is_some() {
+            zomes_to_add.push((dna, zome_pair, coordinator_dependency));
+        }
+    }
 
-    let (mut integrity_manifest, mut coordinator_manifest) = match dna_manifest.clone() {
-        DnaManifest::V1(m) => (m.integrity, m.coordinator),
+    let flake_input = FlakeInputReport {
+        name: module_name.clone(),
+        url: remote_source.flake_ref(),
     };
-    if let Some(integrity_zome) = integrity_zome_name.clone() {
-        integrity_manifest
-            .zomes
-            .push(holochain_types::prelude::ZomeManifest {
-                name: integrity_zome.into(),
-                hash: None,
-                location: ZomeLocation::Bundled(PathBuf::from("<NIX_PACKAGE>")),
-                dependencies: None,
-                dylib: None,
-            });
+    let mut file_tree =
+        add_flake_input(file_tree, flake_input.name.clone(), flake_input.url.clone())?;
+
+    let mut zomes = vec![];
+    for (chosen_dna, zome_pair, coordinator_dependency) in zomes_to_add {
+        // The DNA needs to be looked up again for each pair, since adding a zome changes its files
+        let dna = find_nixified_dnas(&file_tree)?
+            .into_iter()
+            .find(|dna| {
+                dna.dna_nix.0.eq(&chosen_dna.dna_nix.0)
+                    && dna.package_name.eq(&chosen_dna.package_name)
+            })
+            .ok_or(holochain_scaffolding_utils::Error::DnaNotFound(
+                chosen_dna.name,
+            ))?;
+
+        add_zome_to_nixified_dna(
+            &mut file_tree,
+            &dna,
+            zome_pair.integrity_zome_name.clone(),
+            zome_pair.coordinator_zome_name.clone(),
+            coordinator_dependency,
+            &format!("inputs'.{module_name}.packages"),
+        )?;
+        zomes.push(AddedZomesReport {
+            dna: dna.name,
+            dna_nix: dna.dna_nix.0,
+            dna_manifest: dna.dna_manifest.0,
+            integrity_zome_name: zome_pair.integrity_zome_name,
+            coordinator_zome_name: zome_pair.coordinator_zome_name,
+        });
     }
-    if let Some(coordinator_zome) = coordinator_zome_name.clone() {
-        coordinator_manifest
-            .zomes
-            .push(holochain_types::prelude::ZomeManifest {
-                name: coordinator_zome.into(),
-                hash: None,
-                location: ZomeLocation::Bundled(PathBuf::from("<NIX_PACKAGE>")),
-                dependencies: integrity_zome_name
-                    .clone()
-                    .map(|name| vec![ZomeDependency { name: name.into() }]),
-                dylib: None,
-            });
-    }
-
-    let new_manifest: DnaManifest = DnaManifestCurrentBuilder::default()
-        .coordinator(coordinator_manifest)
-        .integrity(integrity_manifest)
-        .name(dna_manifest.name())
-        .build()
-        .unwrap()
-        .into();
 
-    insert_file(
-        file_tree,
-        &nixified_dna.dna_manifest.0,
-        &serde_yaml::to_string(&new_manifest)?,
+    let npm_package_name = remote_zome_manifest.npm_package_name;
+    let (package_json_path, _) = get_or_choose_package_json_for_dependency(
+        &file_tree,
+        &npm_package_name,
+        local_npm_package_to_add_the_ui_to,
+        chooser,
+    )?;
+    // Local checkouts are linked relative to the package that depends on them
+    let npm_dependency_source = remote_source.npm_dependency_source(
+        &remote_zome_manifest.npm_package_path,
+        package_json_path.parent().unwrap_or(Path::new("")),
     )?;
 
-    println!("Added the integrity zome {integrity_zome_name:?} and the coordinator zome {coordinator_zome_name:?} to {:?}.", nixified_dna.dna_manifest.0);
+    map_file(&mut file_tree, &package_json_path, |package_json_content| {
+        add_npm_dependency_to_package(
+            &(package_json_path.clone(), package_json_content),
+            &npm_package_name,
+            &npm_dependency_source,
+        )
+    })?;
+    eprintln!("Added dependency {npm_package_name} to {package_json_path:?}.");
+
+    let modified_files = flatten_file_tree(&file_tree)
+        .into_iter()
+        .filter(|(path, content)| content.is_some() && original_files.get(path) != Some(content))
+        .map(|(path, _)| path)
+        .collect();
+
+    let report = ScaffoldRemoteZomeReport {
+        flake_input,
+        zomes,
+        npm_dependency: NpmDependencyReport {
+            package_json: package_json_path,
+            name: npm_package_name,
+            source: npm_dependency_source,
+        },
+        modified_files,
+    };
 
-    Ok(())
+    Ok((file_tree, report))
 }
 
-fn get_or_choose_dna(
-    file_tree: &FileTree,
-    module_name: &String,
-    local_dna_to_add_the_zome_to: Option<String>,
-) -> Result<NixifiedDna, ScaffoldRemoteZomeError> {
-    let nixified_dnas = find_nixified_dnas(&file_tree)?;
-
-    match nixified_dnas.len() {
-        0 => Err(ScaffoldRemoteZomeError::NoDnasFoundError)?,
-        1 => {
-            let nixified_dna = nixified_dnas.first().unwrap();
-            if let Some(local_dna) = local_dna_to_add_the_zome_to {
-                if !nixified_dna.name.eq(&local_dna) {
-                    return Err(ScaffoldRemoteZomeError::NoDnasFoundError);
-                }
-            }
-            Ok(nixified_dna.clone())
-        }
-        _ => {
-            let dna_names: Vec<String> = nixified_dnas.iter().map(|dna| dna.name.clone()).collect();
-
-            let dna_index = match local_dna_to_add_the_zome_to{
-                Some(local_dna) => dna_names
-                    .iter()
-                    .position(|dna_name| dna_name.eq(&local_dna))
-                    .ok_or(NpmScaffoldingUtilsError::NpmPackageNotFoundError(
-                        local_dna.clone(),
-                    ))?,
-                None => {
-                    Select::with_theme(&ColorfulTheme::default())
-                    .with_prompt(format!("Multiple DNAs were found in this repository, choose one to scaffold the {module_name} zome into:",
-                    ))
-                    .default(0)
-                    .items(&dna_names[..])
-                    .interact()?},
-            };
+/// Removes everything that `scaffold_remote_zome` added for the given module: its flake input,
+/// its zomes from the nixified DNAs and their manifests, and its UI package from the NPM packages
+pub fn unscaffold_remote_zome(
+    file_tree: FileTree,
+    module_name: String,
+    remote_npm_package_name: String,
+) -> Result<FileTree, ScaffoldRemoteZomeError> {
+    let mut file_tree = remove_flake_input(file_tree, module_name.clone())?;
 
-            let nixified_dna = &nixified_dnas[dna_index];
-            Ok(nixified_dna.clone())
-        }
+    let zome_packages = format!("inputs'.{module_name}.packages");
+    for dna in find_nixified_dnas(&file_tree)? {
+        remove_zomes_from_nixified_dna(&mut file_tree, &dna, &zome_packages)?;
     }
-}
 
-fn find_nixified_dnas(file_tree: &FileTree) -> Result<Vec<NixifiedDna>, ScaffoldRemoteZomeError> {
-    // Find all dna.yaml files
-    // Go through all nix files, finding `dnaManifest = `
-    // For each DNA, find the ones that are referred to from the nix files, those are the eligible DNAs
-    // Have the user select one of them
-    // Add the zome in the DNA manifest
-    // Add the zome in the nix file, in zomes = {
-    let dna_manifest_re = Regex::new(r#"dnaManifest = (?<dnaManifestPath>./[^;]*);"#)?;
+    if !find_packages_with_npm_dependency(&file_tree, &remote_npm_package_name)?.is_empty() {
+        file_tree = remove_npm_dependency(file_tree, remote_npm_package_name, None)?;
+    }
 
-    let dna_yaml_files = find_files_by_name(file_tree, PathBuf::from("dna.yaml").as_path());
+    Ok(file_tree)
+}
 
-    let mut nixified_dnas: Vec<NixifiedDna> = Vec::new();
+/// Switches an already scaffolded module to the given branch or revision, both in its flake input
+/// and in the NPM dependencies for its UI package
+///
+/// Returns the paths of the package.json files whose dependencies were switched
+pub fn upgrade_remote_zome(
+    file_tree: FileTree,
+    module_name: String,
+    remote_zome_git_branch: String,
+) -> Result<(FileTree, Vec<PathBuf>), ScaffoldRemoteZomeError> {
+    let remote_source = get_remote_zome_source(&file_tree, &module_name)?;
+
+    switch_remote_zome_source(
+        file_tree,
+        module_name,
+        remote_source.with_reference(remote_zome_git_branch)?,
+    )
+}
 
-    let nix_files = find_files_by_extension(file_tree, "nix");
+/// Returns the source of an already scaffolded module, from its flake input
+pub fn get_remote_zome_source(
+    file_tree: &FileTree,
+    module_name: &String,
+) -> Result<RemoteSource, ScaffoldRemoteZomeError> {
+    let flake_nix = file_content(file_tree, PathBuf::from("flake.nix").as_path())?;
+    let flake_input_url = get_flake_input_url(&flake_nix, module_name)?.ok_or(
+        ScaffoldRemoteZomeError::ModuleNotFoundError(module_name.clone()),
+    )?;
 
-    for (dna_manifest_path, dna_manifest_content) in dna_yaml_files {
-        let value: serde_yaml::Value = serde_yaml::from_str(dna_manifest_content.as_str())?;
+    RemoteSource::parse(&flake_input_url, None)
+}
 
-        let serde_yaml::Value::Mapping(mapping) = value else {
-            return Err(ScaffoldRemoteZomeError::MalformedDnaManifest(
-                dna_manifest_path,
-                String::from("root level object is not a mapping"),
-            ));
-        };
+/// Switches an already scaffolded module to the given source, both in its flake input
+/// and in the NPM dependencies for its UI package
+///
+/// Used to go back and forth between a local checkout of the module and its git repository.
+/// Returns the paths of the package.json files whose dependencies were switched
+pub fn switch_remote_zome_source(
+    file_tree: FileTree,
+    module_name: String,
+    new_remote_source: RemoteSource,
+) -> Result<(FileTree, Vec<PathBuf>), ScaffoldRemoteZomeError> {
+    let remote_source = get_remote_zome_source(&file_tree, &module_name)?;
 
-        let Some(serde_yaml::Value::String(dna_name)) = mapping.get("name") else {
-            return Err(ScaffoldRemoteZomeError::MalformedDnaManifest(
-                dna_manifest_path,
-                String::from("manifest does not have a name"),
-            ));
-        };
+    let mut file_tree = set_flake_input_url(
+        file_tree,
+        module_name.clone(),
+        new_remote_source.flake_ref(),
+    )?;
 
-        for (nix_file_path, nix_file_contents) in nix_files.iter() {
-            if let Some(captures) = dna_manifest_re.captures(&nix_file_contents) {
-                let capture = captures.name("dnaManifestPath").unwrap();
-                let dna_manifest_path_in_nix_file = capture.as_str();
+    let package_jsons = find_files_by_name(&file_tree, PathBuf::from("package.json").as_path());
+    let mut switched_package_jsons = vec![];
 
-                let mut nix_file_folder = nix_file_path.clone();
-                nix_file_folder.pop();
+    for (package_json_path, package_json_content) in package_jsons {
+        let json: serde_json::Value = serde_json::from_str(package_json_content.as_str())?;
+        let package_dir = package_json_path.parent().unwrap_or(Path::new(""));
 
-                let full_path = nix_file_folder.join(dna_manifest_path_in_nix_file).clean();
+        for section in NPM_DEPENDENCY_SECTIONS {
+            let Some(serde_json::Value::Object(dependencies)) = json.get(section) else {
+                continue;
+            };
 
-                if full_path.eq(&dna_manifest_path) {
-                    nixified_dnas.push(NixifiedDna {
-                        name: dna_name.clone(),
-                        dna_nix: (nix_file_path.clone(), nix_file_contents.clone()),
-                        dna_manifest: (dna_manifest_path.clone(), dna_manifest_content.clone()),
-                        dna_manifest_reference_index: capture.start(),
-                    });
+            for (dependency, source) in dependencies {
+                let serde_json::Value::String(source) = source else {
+                    continue;
+                };
+                // Keep the path of the package inside the repository, like "ui"
+                let Some(npm_package_path) = remote_source.npm_package_path_in(source, package_dir)
+                else {
+                    continue;
+                };
+                let new_source =
+                    new_remote_source.npm_dependency_source(&npm_package_path, package_dir)?;
+
+                map_file(&mut file_tree, &package_json_path, |package_json_content| {
+                    update_npm_dependency_source_in_package(
+                        &(package_json_path.clone(), package_json_content),
+                        dependency,
+                        &new_source,
+                    )
+                })?;
+                eprintln!("Set dependency {dependency} to {new_source} in {package_json_path:?}.");
+                if !switched_package_jsons.contains(&package_json_path) {
+                    switched_package_jsons.push(package_json_path.clone());
                 }
             }
         }
     }
 
-    Ok(nixified_dnas)
+    Ok((file_tree, switched_package_jsons))
 }
 
 #[cfg(test)]
@@ -346,17 +416,20 @@ mod tests {
     use pretty_assertions::assert_eq;
     use build_fs_tree::{dir, file};
     use file_tree_utils::file_content;
+    use holochain_scaffolding_utils::test_utils::empty_dna_yaml;
+    use prompt_utils::{DialoguerChooser, ScriptedChooser};
 
     #[test]
     fn multiple_package_test() {
         let repo: FileTree = dir! {
             "flake.nix" => file!(default_flake_nix()),
-            "dna.nix" => file!(empty_dna_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
             "workdir" => dir! {
                 "dna.yaml" => file!(empty_dna_yaml("another_dna"))
             },
             "dna.yaml" => file!(empty_dna_yaml("mydna")),
             "package.json" => file!(empty_package_json("root")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
             "packages" => dir! {
                 "package1" => dir! {
                     "package.json" => file!(empty_package_json("package1"))
@@ -367,17 +440,14 @@ mod tests {
             }
         };
 
-        let repo = scaffold_remote_zome(
+        let (repo, _) = scaffold_remote_zome(
             repo,
             "profiles".into(),
-            Some("profiles_integrity".into()),
-            Some("profiles".into()),
-            "github:holochain-open-dev/profiles".into(),
-            Some("nixify".into()),
-            "@holochain-open-dev/profiles".into(),
-            PathBuf::from("ui"),
-            None,
+            profiles_manifest(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/nixify".into(), None).unwrap(),
+            vec![Some("mydna".into())],
             Some("package1".into()),
+            &DialoguerChooser::new(true),
         )
         .unwrap();
 
@@ -392,31 +462,34 @@ mod tests {
   "dependencies": {
     "@holochain-open-dev/profiles": "github:holochain-open-dev/profiles#nixify&path:ui"
   }
-}"#
+}
+"#
         );
 
         assert_eq!(
             file_content(&repo, PathBuf::from("dna.yaml").as_path()).unwrap(),
-            r#"manifest_version: '1'
+            r#"
+---
+manifest_version: "1"
 name: mydna
 integrity:
-  network_seed: null
-  properties: null
+  network_seed: ~
+  properties: ~
   origin_time: 1709638576394039
   zomes:
   - name: profiles_integrity
-    hash: null
+    hash: ~
     bundled: <NIX_PACKAGE>
-    dependencies: null
-    dylib: null
+    dependencies: ~
+    dylib: ~
 coordinator:
   zomes:
   - name: profiles
-    hash: null
+    hash: ~
     bundled: <NIX_PACKAGE>
     dependencies:
     - name: profiles_integrity
-    dylib: null
+    dylib: ~
 "#
         );
 
@@ -495,29 +568,531 @@ coordinator:
         );
     }
 
-    fn empty_package_json(package_name: &str) -> String {
-        format!(
-            r#"{{
-  "name": "{package_name}",
-  "dependencies": {{}}
-}}
-"#
+    #[test]
+    fn multiple_zome_pairs_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "provider" => dir! {
+                "dna.nix" => file!(two_dnas_nix().replace("my_dna", "provider_dna")),
+                "dna.yaml" => file!(empty_dna_yaml("provider")),
+            },
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let manifest: RemoteZomeManifest = serde_yaml::from_str(
+            r#"
+zomes:
+  - integrity_zome_name: file_storage_integrity
+    coordinator_zome_name: file_storage_gateway
+  - integrity_zome_name: file_storage_integrity
+    coordinator_zome_name: file_storage_provider
+npm_package_name: "@holochain-open-dev/file-storage"
+npm_package_path: ui
+"#,
         )
+        .unwrap();
+
+        let (repo, report) = scaffold_remote_zome(
+            repo,
+            "file_storage".into(),
+            manifest,
+            RemoteSource::parse(&"github:holochain-open-dev/file-storage".into(), None).unwrap(),
+            vec![None, Some("provider".into())],
+            None,
+            &ScriptedChooser::new(vec!["mydna".into()]),
+        )
+        .unwrap();
+
+        let dna_nix = file_content(&repo, PathBuf::from("dna.nix").as_path()).unwrap();
+        assert!(dna_nix.contains(
+            "file_storage_gateway = inputs'.file_storage.packages.file_storage_gateway;"
+        ));
+        assert!(!dna_nix.contains("file_storage_provider"));
+
+        let provider_dna_nix =
+            file_content(&repo, PathBuf::from("provider/dna.nix").as_path()).unwrap();
+        assert!(provider_dna_nix.contains(
+            "file_storage_provider = inputs'.file_storage.packages.file_storage_provider;"
+        ));
+        assert!(provider_dna_nix.contains(
+            "file_storage_integrity = inputs'.file_storage.packages.file_storage_integrity;"
+        ));
+
+        let flake_nix = file_content(&repo, PathBuf::from("flake.nix").as_path()).unwrap();
+        assert_eq!(flake_nix.matches("file_storage.url").count(), 1);
+
+        assert!(file_content(&repo, PathBuf::from("package.json").as_path())
+            .unwrap()
+            .contains("@holochain-open-dev/file-storage"));
+
+        assert_eq!(
+            report.zomes,
+            vec![
+                AddedZomesReport {
+                    dna: "mydna".into(),
+                    dna_nix: "dna.nix".into(),
+                    dna_manifest: "dna.yaml".into(),
+                    integrity_zome_name: Some("file_storage_integrity".into()),
+                    coordinator_zome_name: Some("file_storage_gateway".into()),
+                },
+                AddedZomesReport {
+                    dna: "provider".into(),
+                    dna_nix: "provider/dna.nix".into(),
+                    dna_manifest: "provider/dna.yaml".into(),
+                    integrity_zome_name: Some("file_storage_integrity".into()),
+                    coordinator_zome_name: Some("file_storage_provider".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            report.npm_dependency,
+            NpmDependencyReport {
+                package_json: "package.json".into(),
+                name: "@holochain-open-dev/file-storage".into(),
+                source: "github:holochain-open-dev/file-storage#path:ui".into(),
+            }
+        );
+        assert_eq!(
+            report.modified_files,
+            vec![
+                PathBuf::from("dna.nix"),
+                PathBuf::from("dna.yaml"),
+                PathBuf::from("flake.nix"),
+                PathBuf::from("package.json"),
+                PathBuf::from("provider/dna.nix"),
+                PathBuf::from("provider/dna.yaml"),
+            ]
+        );
     }
 
-    fn empty_dna_yaml(dna_name: &str) -> String {
-        format!(
+    /// Chooses the last of the items, to pick the DNAs that are found after others with the same name
+    struct LastItemChooser;
+
+    impl Chooser for LastItemChooser {
+        fn choose(
+            &self,
+            _prompt: &String,
+            items: &Vec<String>,
+            _default: usize,
+        ) -> Result<usize, PromptError> {
+            Ok(items.len() - 1)
+        }
+
+        fn choose_multiple(
+            &self,
+            _prompt: &String,
+            items: &Vec<String>,
+            _defaults: &Vec<bool>,
+        ) -> Result<Vec<usize>, PromptError> {
+            Ok(vec![items.len() - 1])
+        }
+    }
+
+    #[test]
+    fn zome_pairs_sharing_their_integrity_zome_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let manifest: RemoteZomeManifest = serde_yaml::from_str(
+            r#"
+zomes:
+  - integrity_zome_name: file_storage_integrity
+    coordinator_zome_name: file_storage_gateway
+  - integrity_zome_name: file_storage_integrity
+    coordinator_zome_name: file_storage_provider
+npm_package_name: "@holochain-open-dev/file-storage"
+npm_package_path: ui
+"#,
+        )
+        .unwrap();
+
+        let (repo, report) = scaffold_remote_zome(
+            repo,
+            "file_storage".into(),
+            manifest,
+            RemoteSource::parse(&"github:holochain-open-dev/file-storage".into(), None).unwrap(),
+            vec![Some("mydna".into()), Some("mydna".into())],
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("dna.yaml").as_path()).unwrap(),
             r#"
 ---
 manifest_version: "1"
-name: {dna_name}
+name: mydna
 integrity:
   network_seed: ~
   properties: ~
   origin_time: 1709638576394039
-  zomes: []
+  zomes:
+  - name: file_storage_integrity
+    hash: ~
+    bundled: <NIX_PACKAGE>
+    dependencies: ~
+    dylib: ~
 coordinator:
-  zomes: []
+  zomes:
+  - name: file_storage_gateway
+    hash: ~
+    bundled: <NIX_PACKAGE>
+    dependencies:
+    - name: file_storage_integrity
+    dylib: ~
+  - name: file_storage_provider
+    hash: ~
+    bundled: <NIX_PACKAGE>
+    dependencies:
+    - name: file_storage_integrity
+    dylib: ~
+"#
+        );
+        assert_eq!(report.zomes[1].integrity_zome_name, None);
+        assert_eq!(
+            report.zomes[1].coordinator_zome_name,
+            Some("file_storage_provider".into())
+        );
+    }
+
+    #[test]
+    fn dnas_with_the_same_name_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "workdir" => dir! {
+                "dna.nix" => file!(two_dnas_nix()),
+                "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            },
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let (repo, report) = scaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            profiles_manifest(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles".into(), None).unwrap(),
+            vec![],
+            None,
+            &LastItemChooser,
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("dna.nix").as_path()).unwrap(),
+            two_dnas_nix()
+        );
+        assert!(
+            file_content(&repo, PathBuf::from("workdir/dna.nix").as_path())
+                .unwrap()
+                .contains("profiles = inputs'.profiles.packages.profiles;")
+        );
+        assert_eq!(report.zomes[0].dna_nix, PathBuf::from("workdir/dna.nix"));
+    }
+
+    #[test]
+    fn already_scaffolded_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let scaffold = |repo: FileTree| {
+            scaffold_remote_zome(
+                repo,
+                "profiles".into(),
+                profiles_manifest(),
+                RemoteSource::parse(&"github:holochain-open-dev/profiles/nixify".into(), None)
+                    .unwrap(),
+                vec![],
+                None,
+                &DialoguerChooser::new(true),
+            )
+        };
+
+        let (repo, _) = scaffold(repo).unwrap();
+        let result = scaffold(repo);
+
+        assert!(matches!(
+            result,
+            Err(ScaffoldRemoteZomeError::ZomeAlreadyExistsError(zome, dna))
+                if zome == "profiles_integrity" && dna == "mydna"
+        ));
+    }
+
+    #[test]
+    fn unscaffold_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "package.json" => file!(empty_package_json("root")),
+            "pnpm-workspace.yaml" => file!(String::from("packages:\n  - 'packages/*'\n")),
+            "packages" => dir! {
+                "package1" => dir! {
+                    "package.json" => file!(empty_package_json("package1"))
+                },
+            }
+        };
+
+        let (repo, _) = scaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            profiles_manifest(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/nixify".into(), None).unwrap(),
+            vec![],
+            Some("package1".into()),
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        let repo = unscaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            "@holochain-open-dev/profiles".into(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("flake.nix").as_path()).unwrap(),
+            default_flake_nix()
+        );
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("dna.nix").as_path()).unwrap(),
+            two_dnas_nix()
+        );
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("dna.yaml").as_path()).unwrap(),
+            empty_dna_yaml("mydna")
+        );
+
+        assert_eq!(
+            file_content(
+                &repo,
+                PathBuf::from("packages/package1/package.json").as_path()
+            )
+            .unwrap(),
+            r#"{
+  "name": "package1",
+  "dependencies": {}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn upgrade_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let (repo, _) = scaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            profiles_manifest(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/nixify".into(), None).unwrap(),
+            vec![],
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        let (repo, switched_package_jsons) =
+            upgrade_remote_zome(repo, "profiles".into(), "main".into()).unwrap();
+
+        assert_eq!(switched_package_jsons, vec![PathBuf::from("package.json")]);
+
+        assert!(file_content(&repo, PathBuf::from("flake.nix").as_path())
+            .unwrap()
+            .contains(r#"profiles.url = "github:holochain-open-dev/profiles/main";"#));
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("package.json").as_path()).unwrap(),
+            r#"{
+  "name": "root",
+  "dependencies": {
+    "@holochain-open-dev/profiles": "github:holochain-open-dev/profiles#main&path:ui"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn switch_to_local_path_and_back_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "packages" => dir! {
+                "app" => dir! {
+                    "package.json" => file!(empty_package_json("app"))
+                },
+            }
+        };
+
+        let (repo, _) = scaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            profiles_manifest(),
+            RemoteSource::parse(&"path:../profiles".into(), None).unwrap(),
+            vec![],
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert!(file_content(&repo, PathBuf::from("flake.nix").as_path())
+            .unwrap()
+            .contains(r#"profiles.url = "path:../profiles";"#));
+        assert!(
+            file_content(&repo, PathBuf::from("packages/app/package.json").as_path())
+                .unwrap()
+                .contains(r#""@holochain-open-dev/profiles": "file:../../../profiles/ui""#)
+        );
+
+        let (repo, switched_package_jsons) = switch_remote_zome_source(
+            repo,
+            "profiles".into(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/main".into(), None).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            switched_package_jsons,
+            vec![PathBuf::from("packages/app/package.json")]
+        );
+
+        assert!(file_content(&repo, PathBuf::from("flake.nix").as_path())
+            .unwrap()
+            .contains(r#"profiles.url = "github:holochain-open-dev/profiles/main";"#));
+        assert!(
+            file_content(&repo, PathBuf::from("packages/app/package.json").as_path())
+                .unwrap()
+                .contains(
+                    r#""@holochain-open-dev/profiles": "github:holochain-open-dev/profiles#main&path:ui""#
+                )
+        );
+    }
+
+    #[test]
+    fn switch_dev_dependency_test() {
+        let repo: FileTree = dir! {
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(two_dnas_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+            "package.json" => file!(empty_package_json("root")),
+        };
+
+        let (mut repo, _) = scaffold_remote_zome(
+            repo,
+            "profiles".into(),
+            profiles_manifest(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/nixify".into(), None).unwrap(),
+            vec![],
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+        map_file(&mut repo, Path::new("package.json"), |package_json| {
+            Ok::<_, FileTreeError>(package_json.replace("\"dependencies\"", "\"devDependencies\""))
+        })
+        .unwrap();
+
+        let (repo, switched_package_jsons) = switch_remote_zome_source(
+            repo,
+            "profiles".into(),
+            RemoteSource::parse(&"github:holochain-open-dev/profiles/main".into(), None).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(switched_package_jsons, vec![PathBuf::from("package.json")]);
+        assert_eq!(
+            file_content(&repo, PathBuf::from("package.json").as_path()).unwrap(),
+            r#"{
+  "name": "root",
+  "devDependencies": {
+    "@holochain-open-dev/profiles": "github:holochain-open-dev/profiles#main&path:ui"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn read_remote_zome_manifest_test() {
+        let module_path = std::env::temp_dir().join(format!(
+            "read_remote_zome_manifest_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(module_path.join("ui")).unwrap();
+        std::fs::write(
+            module_path.join(REMOTE_ZOME_MANIFEST_FILE_NAME),
+            r#"
+zomes:
+  - integrity_zome_name: profiles_integrity
+    coordinator_zome_name: profiles
+npm_package_name: "@holochain-open-dev/profiles"
+npm_package_path: ui
+"#,
+        )
+        .unwrap();
+        std::fs::write(module_path.join("malformed.yaml"), "zomes: 3\n").unwrap();
+
+        let from_directory = read_remote_zome_manifest(&module_path);
+        let from_file =
+            read_remote_zome_manifest(&module_path.join(REMOTE_ZOME_MANIFEST_FILE_NAME));
+        let missing = read_remote_zome_manifest(&module_path.join("ui"));
+        let malformed = read_remote_zome_manifest(&module_path.join("malformed.yaml"));
+        std::fs::remove_dir_all(&module_path).unwrap();
+
+        assert_eq!(from_directory.unwrap(), profiles_manifest());
+        assert_eq!(from_file.unwrap(), profiles_manifest());
+        assert!(matches!(
+            missing,
+            Err(ScaffoldRemoteZomeError::RemoteZomeManifestNotFoundError(path, _))
+                if path == module_path.join("ui").join(REMOTE_ZOME_MANIFEST_FILE_NAME)
+        ));
+        assert!(matches!(
+            malformed,
+            Err(ScaffoldRemoteZomeError::SerdeYamlError(_))
+        ));
+    }
+
+    fn profiles_manifest() -> RemoteZomeManifest {
+        serde_yaml::from_str(
+            r#"
+zomes:
+  - integrity_zome_name: profiles_integrity
+    coordinator_zome_name: profiles
+npm_package_name: "@holochain-open-dev/profiles"
+npm_package_path: ui
+"#,
+        )
+        .unwrap()
+    }
+
+    fn empty_package_json(package_name: &str) -> String {
+        format!(
+            r#"{{
+  "name": "{package_name}",
+  "dependencies": {{}}
+}}
 "#
         )
     }
@@ -567,7 +1142,8 @@ coordinator:
         )
     }
 
-    fn empty_dna_nix() -> String {
+    /// A dna.nix declaring both `my_dna` and `another_dna`, built from `./workdir/dna.yaml`
+    fn two_dnas_nix() -> String {
         String::from(
             r#"{ inputs, ... }:
 
@@ -0,0 +1,113 @@
+use npm_scaffolding_utils::PackageManager;
+use std::{path::Path, process::Command};
+use sync_npm_git_dependencies_with_nix::synchronize_npm_git_dependencies_sources;
+
+use crate::ScaffoldRemoteZomeError;
+
+/// Step to run after the scaffolded files have been written, to bring the lock files and the installed
+/// dependencies up to date with them
+#[derive(Debug, Clone)]
+pub enum PostScaffoldHook {
+    /// Updates all the flake inputs with `nix flake update`
+    NixFlakeUpdate,
+    /// Only updates the flake input with the given name, with `nix flake lock --update-input`
+    NixFlakeLockUpdateInput(String),
+    /// Points the NPM git dependencies to the revisions locked in flake.lock
+    SynchronizeNpmGitDependencies,
+    /// Installs the NPM dependencies with the given package manager
+    InstallNpmDependencies(PackageManager),
+}
+
+impl PostScaffoldHook {
+    /// Command line for the hook, as shown to the user
+    pub fn description(&self) -> String {
+        match self.command() {
+            Some((program, args)) => format!("{program} {}", args.join(" ")),
+            None => String::from("sync-npm-git-dependencies-with-nix"),
+        }
+    }
+
+    /// Program and arguments to run for the hook, or `None` if it doesn't run an external command
+    fn command(&self) -> Option<(String, Vec<String>)> {
+        match self {
+            PostScaffoldHook::NixFlakeUpdate => {
+                Some(("nix".into(), vec!["flake".into(), "update".into()]))
+            }
+            PostScaffoldHook::NixFlakeLockUpdateInput(input) => Some((
+                "nix".into(),
+                vec![
+                    "flake".into(),
+                    "lock".into(),
+                    "--update-input".into(),
+                    input.clone(),
+                ],
+            )),
+            PostScaffoldHook::SynchronizeNpmGitDependencies => None,
+            PostScaffoldHook::InstallNpmDependencies(package_manager) => {
+                let install_command = package_manager.install_command();
+                let mut words = install_command.split_whitespace().map(String::from);
+                Some((words.next()?, words.collect()))
+            }
+        }
+    }
+
+    /// Runs the hook in the given directory, erroring if its command fails
+    pub fn run(&self, path: &Path) -> Result<(), ScaffoldRemoteZomeError> {
+        eprintln!("Running {}...", self.description());
+
+        let Some((program, args)) = self.command() else {
+            synchronize_npm_git_dependencies_sources(path)?;
+            return Ok(());
+        };
+
+        // The output of the commands is only progress information, like the messages of the scaffolding functions
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(path)
+            .stdout(std::io::stderr())
+            .status()
+            .map_err(|err| {
+                ScaffoldRemoteZomeError::PostScaffoldHookError(self.description(), err.to_string())
+            })?;
+
+        if !status.success() {
+            return Err(ScaffoldRemoteZomeError::PostScaffoldHookError(
+                self.description(),
+                status.to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Hooks to run after the module has been scaffolded, upgraded or switched to another source
+///
+/// With `update_input_only`, only the flake input of the module is updated instead of all of them
+pub fn post_scaffold_hooks(
+    module_name: &String,
+    update_input_only: bool,
+    package_manager: PackageManager,
+) -> Vec<PostScaffoldHook> {
+    let nix_hook = match update_input_only {
+        true => PostScaffoldHook::NixFlakeLockUpdateInput(module_name.clone()),
+        false => PostScaffoldHook::NixFlakeUpdate,
+    };
+
+    vec![
+        nix_hook,
+        PostScaffoldHook::SynchronizeNpmGitDependencies,
+        PostScaffoldHook::InstallNpmDependencies(package_manager),
+    ]
+}
+
+/// Runs the given hooks in order, stopping at the first one that fails
+pub fn run_post_scaffold_hooks(
+    hooks: &Vec<PostScaffoldHook>,
+    path: &Path,
+) -> Result<(), ScaffoldRemoteZomeError> {
+    for hook in hooks {
+        hook.run(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,495 @@
+use std::path::{Component, Path, PathBuf};
+
+use npm_scaffolding_utils::{GitHost, NpmDependencySpec};
+
+use crate::ScaffoldRemoteZomeError;
+
+/// Where the repository of a module lives, parsed from the URL passed to `scaffold_remote_zome`
+///
+/// Knows how to refer to the repository both as a nix flake input and as an NPM dependency
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteSource {
+    /// `github:owner/repo`, optionally followed by `/branch`
+    GitHub {
+        owner: String,
+        repo: String,
+        reference: Option<String>,
+    },
+    /// `gitlab:owner/repo`, optionally followed by `/branch`
+    GitLab {
+        owner: String,
+        repo: String,
+        reference: Option<String>,
+    },
+    /// `git+https://host/repo.git`, optionally followed by `?ref=branch` or `?rev=commit`
+    GitHttps {
+        url: String,
+        reference: Option<String>,
+    },
+    /// `git+ssh://git@host/repo.git`, optionally followed by `?ref=branch` or `?rev=commit`
+    GitSsh {
+        url: String,
+        reference: Option<String>,
+    },
+    /// `path:../repo`, a local checkout of the repository, with relative paths being relative to the root of the project
+    Path { path: PathBuf },
+}
+
+impl RemoteSource {
+    /// Parses the given URL, with the branch or revision either embedded in it or given separately
+    ///
+    /// The separately given reference takes precedence over the one in the URL
+    pub fn parse(
+        url: &String,
+        reference: Option<String>,
+    ) -> Result<RemoteSource, ScaffoldRemoteZomeError> {
+        let invalid_error = || ScaffoldRemoteZomeError::InvalidRemoteSourceError(url.clone());
+
+        let (scheme, rest) = url.split_once(':').ok_or_else(invalid_error)?;
+
+        match scheme {
+            "github" | "gitlab" => {
+                let segments: Vec<&str> = rest.splitn(3, '/').collect();
+                if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+                    return Err(invalid_error());
+                }
+                let owner = segments[0].to_string();
+                let repo = segments[1].to_string();
+                let reference = reference.or(segments.get(2).map(|r| r.to_string()));
+
+                Ok(match scheme {
+                    "github" => RemoteSource::GitHub {
+                        owner,
+                        repo,
+                        reference,
+                    },
+                    _ => RemoteSource::GitLab {
+                        owner,
+                        repo,
+                        reference,
+                    },
+                })
+            }
+            "git+https" | "git+ssh" => {
+                if !rest.starts_with("//") {
+                    return Err(invalid_error());
+                }
+                let (url_without_query, query) = match url.split_once('?') {
+                    Some((url_without_query, query)) => (url_without_query, Some(query)),
+                    None => (url.as_str(), None),
+                };
+                let reference = reference.or(query.and_then(|query| {
+                    query.split('&').find_map(|argument| {
+                        argument
+                            .strip_prefix("ref=")
+                            .or(argument.strip_prefix("rev="))
+                            .map(|r| r.to_string())
+                    })
+                }));
+                let url = url_without_query.to_string();
+
+                Ok(match scheme {
+                    "git+https" => RemoteSource::GitHttps { url, reference },
+                    _ => RemoteSource::GitSsh { url, reference },
+                })
+            }
+            "path" => {
+                if rest.is_empty() {
+                    return Err(invalid_error());
+                }
+                Ok(RemoteSource::Path {
+                    path: PathBuf::from(rest),
+                })
+            }
+            _ => Err(invalid_error()),
+        }
+    }
+
+    /// The branch or revision of the repository, if any
+    pub fn reference(&self) -> Option<String> {
+        match self {
+            RemoteSource::GitHub { reference, .. }
+            | RemoteSource::GitLab { reference, .. }
+            | RemoteSource::GitHttps { reference, .. }
+            | RemoteSource::GitSsh { reference, .. } => reference.clone(),
+            RemoteSource::Path { .. } => None,
+        }
+    }
+
+    /// Returns the same source pointing to the given branch or revision
+    pub fn with_reference(self, new_reference: String) -> Result<Self, ScaffoldRemoteZomeError> {
+        match self {
+            RemoteSource::GitHub { owner, repo, .. } => Ok(RemoteSource::GitHub {
+                owner,
+                repo,
+                reference: Some(new_reference),
+            }),
+            RemoteSource::GitLab { owner, repo, .. } => Ok(RemoteSource::GitLab {
+                owner,
+                repo,
+                reference: Some(new_reference),
+            }),
+            RemoteSource::GitHttps { url, .. } => Ok(RemoteSource::GitHttps {
+                url,
+                reference: Some(new_reference),
+            }),
+            RemoteSource::GitSsh { url, .. } => Ok(RemoteSource::GitSsh {
+                url,
+                reference: Some(new_reference),
+            }),
+            RemoteSource::Path { path } => {
+                Err(ScaffoldRemoteZomeError::UnsupportedFlakeInputUrlError(
+                    format!("path:{}", path.to_str().unwrap()),
+                ))
+            }
+        }
+    }
+
+    /// URL of the repository without its branch or revision, as it appears at the start of its NPM dependency sources
+    pub fn npm_git_url(&self) -> String {
+        match self {
+            RemoteSource::GitHub { owner, repo, .. } => format!("github:{owner}/{repo}"),
+            RemoteSource::GitLab { owner, repo, .. } => format!("gitlab:{owner}/{repo}"),
+            RemoteSource::GitHttps { url, .. } | RemoteSource::GitSsh { url, .. } => url.clone(),
+            RemoteSource::Path { path } => format!("file:{}", path.to_str().unwrap()),
+        }
+    }
+
+    /// URL to use for the flake input of the module
+    pub fn flake_ref(&self) -> String {
+        match self {
+            RemoteSource::GitHub {
+                reference: Some(reference),
+                ..
+            }
+            | RemoteSource::GitLab {
+                reference: Some(reference),
+                ..
+            } => format!("{}/{reference}", self.npm_git_url()),
+            RemoteSource::GitHub { .. } | RemoteSource::GitLab { .. } => self.npm_git_url(),
+            RemoteSource::GitHttps { url, reference } | RemoteSource::GitSsh { url, reference } => {
+                match reference {
+                    // Flakes only accept full commit hashes as revisions, everything else is a branch or a tag
+                    Some(reference) if is_commit_hash(reference) => {
+                        format!("{url}?rev={reference}")
+                    }
+                    Some(reference) => format!("{url}?ref={reference}"),
+                    None => url.clone(),
+                }
+            }
+            RemoteSource::Path { path } => format!("path:{}", path.to_str().unwrap()),
+        }
+    }
+
+    /// Source to use for the NPM dependency on the package at the given path inside the repository,
+    /// from the package located in `dependent_package_dir`
+    ///
+    /// Local checkouts are linked with a `file:` source, which NPM resolves relative to the dependent package
+    pub fn npm_dependency_source(
+        &self,
+        npm_package_path: &Path,
+        dependent_package_dir: &Path,
+    ) -> Result<String, ScaffoldRemoteZomeError> {
+        let spec = match self {
+            RemoteSource::Path { path } => {
+                let package_path = path.join(npm_package_path);
+                let package_path = match package_path.is_absolute() {
+                    true => package_path,
+                    false => normalize_path(dependent_package_dir)
+                        .components()
+                        .map(|_| Path::new(".."))
+                        .collect::<PathBuf>()
+                        .join(package_path),
+                };
+                NpmDependencySpec::File(path_to_string(&normalize_path(&package_path))?)
+            }
+            RemoteSource::GitHub { owner, repo, .. } => NpmDependencySpec::HostedGit {
+                host: GitHost::GitHub,
+                owner: owner.clone(),
+                repo: repo.clone(),
+                committish: self.reference(),
+                path: Some(path_to_string(npm_package_path)?),
+            },
+            RemoteSource::GitLab { owner, repo, .. } => NpmDependencySpec::HostedGit {
+                host: GitHost::GitLab,
+                owner: owner.clone(),
+                repo: repo.clone(),
+                committish: self.reference(),
+                path: Some(path_to_string(npm_package_path)?),
+            },
+            RemoteSource::GitHttps { url, reference } | RemoteSource::GitSsh { url, reference } => {
+                NpmDependencySpec::Git {
+                    url: url.clone(),
+                    committish: reference.clone(),
+                    path: Some(path_to_string(npm_package_path)?),
+                }
+            }
+        };
+
+        Ok(spec.to_string())
+    }
+
+    /// Inverse of `npm_dependency_source`: if the given NPM dependency source points to a package of this repository,
+    /// returns the path of that package inside the repository
+    pub fn npm_package_path_in(
+        &self,
+        dependency_source: &String,
+        dependent_package_dir: &Path,
+    ) -> Option<PathBuf> {
+        let spec = NpmDependencySpec::parse(dependency_source).ok()?;
+
+        match (self, spec) {
+            (
+                RemoteSource::Path { path },
+                NpmDependencySpec::File(package_path)
+                | NpmDependencySpec::Link(package_path)
+                | NpmDependencySpec::Path(package_path),
+            ) => {
+                let package_path = normalize_path(&dependent_package_dir.join(package_path));
+                let npm_package_path = package_path.strip_prefix(normalize_path(path)).ok()?;
+                Some(npm_package_path.to_path_buf())
+            }
+            (
+                RemoteSource::GitHub { owner, repo, .. },
+                NpmDependencySpec::HostedGit {
+                    host: GitHost::GitHub,
+                    owner: spec_owner,
+                    repo: spec_repo,
+                    path,
+                    ..
+                },
+            )
+            | (
+                RemoteSource::GitLab { owner, repo, .. },
+                NpmDependencySpec::HostedGit {
+                    host: GitHost::GitLab,
+                    owner: spec_owner,
+                    repo: spec_repo,
+                    path,
+                    ..
+                },
+            ) => (spec_owner.eq(owner) && spec_repo.eq(repo))
+                .then(|| PathBuf::from(path.unwrap_or_default())),
+            (
+                RemoteSource::GitHttps { url, .. } | RemoteSource::GitSsh { url, .. },
+                NpmDependencySpec::Git {
+                    url: spec_url,
+                    path,
+                    ..
+                },
+            ) => spec_url
+                .eq(url)
+                .then(|| PathBuf::from(path.unwrap_or_default())),
+            _ => None,
+        }
+    }
+}
+
+fn path_to_string(path: &Path) -> Result<String, ScaffoldRemoteZomeError> {
+    path.to_str()
+        .map(|path| path.to_string())
+        .ok_or_else(|| ScaffoldRemoteZomeError::NonUtf8PathError(path.to_path_buf()))
+}
+
+/// Resolves the "." and ".." components of the given path without accessing the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn is_commit_hash(reference: &String) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(url: &str, reference: Option<&str>) -> RemoteSource {
+        RemoteSource::parse(&url.to_string(), reference.map(|r| r.to_string())).unwrap()
+    }
+
+    #[test]
+    fn github_test() {
+        let source = parse("github:holochain-open-dev/profiles", Some("nixify"));
+        assert_eq!(
+            source,
+            parse("github:holochain-open-dev/profiles/nixify", None)
+        );
+
+        assert_eq!(
+            source.flake_ref(),
+            "github:holochain-open-dev/profiles/nixify"
+        );
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), Path::new(""))
+                .unwrap(),
+            "github:holochain-open-dev/profiles#nixify&path:ui"
+        );
+
+        assert_eq!(
+            source.npm_package_path_in(
+                &"github:holochain-open-dev/profiles#main&path:ui".into(),
+                Path::new("")
+            ),
+            Some(PathBuf::from("ui"))
+        );
+        assert_eq!(
+            source.npm_package_path_in(
+                &"github:holochain-open-dev/profiles-extra#main".into(),
+                Path::new("")
+            ),
+            None
+        );
+
+        let source = parse("github:holochain-open-dev/profiles", None);
+        assert_eq!(source.flake_ref(), "github:holochain-open-dev/profiles");
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), Path::new(""))
+                .unwrap(),
+            "github:holochain-open-dev/profiles#path:ui"
+        );
+    }
+
+    #[test]
+    fn gitlab_test() {
+        let source = parse("gitlab:owner/module/v0.1", Some("main"));
+
+        assert_eq!(source.flake_ref(), "gitlab:owner/module/main");
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("packages/ui"), Path::new(""))
+                .unwrap(),
+            "gitlab:owner/module#main&path:packages/ui"
+        );
+    }
+
+    #[test]
+    fn git_https_test() {
+        let source = parse(
+            "git+https://codeberg.org/owner/module.git?ref=develop",
+            None,
+        );
+        assert_eq!(
+            source,
+            RemoteSource::GitHttps {
+                url: "git+https://codeberg.org/owner/module.git".into(),
+                reference: Some("develop".into()),
+            }
+        );
+
+        assert_eq!(
+            source.flake_ref(),
+            "git+https://codeberg.org/owner/module.git?ref=develop"
+        );
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), Path::new(""))
+                .unwrap(),
+            "git+https://codeberg.org/owner/module.git#develop&path:ui"
+        );
+
+        let revision = "0123456789abcdef0123456789abcdef01234567";
+        let source = source.with_reference(revision.into()).unwrap();
+        assert_eq!(
+            source.flake_ref(),
+            format!("git+https://codeberg.org/owner/module.git?rev={revision}")
+        );
+        assert_eq!(source, parse(&source.flake_ref(), None));
+    }
+
+    #[test]
+    fn git_ssh_test() {
+        let source = parse("git+ssh://git@example.com/owner/module.git", Some("main"));
+
+        assert_eq!(
+            source.flake_ref(),
+            "git+ssh://git@example.com/owner/module.git?ref=main"
+        );
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), Path::new(""))
+                .unwrap(),
+            "git+ssh://git@example.com/owner/module.git#main&path:ui"
+        );
+    }
+
+    #[test]
+    fn path_test() {
+        let source = parse("path:/home/user/module", None);
+
+        assert_eq!(source.flake_ref(), "path:/home/user/module");
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), Path::new(""))
+                .unwrap(),
+            "file:/home/user/module/ui"
+        );
+        assert!(source.with_reference("main".into()).is_err());
+
+        let source = parse("path:../module", None);
+        let dependent_package_dir = Path::new("packages/app");
+
+        assert_eq!(source.flake_ref(), "path:../module");
+        assert_eq!(
+            source
+                .npm_dependency_source(Path::new("ui"), dependent_package_dir)
+                .unwrap(),
+            "file:../../../module/ui"
+        );
+        assert_eq!(
+            source.npm_package_path_in(&"file:../../../module/ui".into(), dependent_package_dir),
+            Some(PathBuf::from("ui"))
+        );
+        assert_eq!(
+            source.npm_package_path_in(&"file:../../other/ui".into(), dependent_package_dir),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_path_test() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let source = RemoteSource::Path {
+            path: PathBuf::from(OsStr::from_bytes(b"../module\xff")),
+        };
+
+        assert!(matches!(
+            source.npm_dependency_source(Path::new("ui"), Path::new("")),
+            Err(ScaffoldRemoteZomeError::NonUtf8PathError(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_source_test() {
+        for url in [
+            "holochain-open-dev/profiles",
+            "github:profiles",
+            "https://github.com/holochain-open-dev/profiles",
+            "git+https:codeberg.org/owner/module.git",
+        ] {
+            assert!(matches!(
+                RemoteSource::parse(&url.to_string(), None),
+                Err(ScaffoldRemoteZomeError::InvalidRemoteSourceError(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use build_fs_tree::{Build, MergeableFileSystemTree};
+use clap::Parser;
+use colored::Colorize;
+use prompt_utils::build_chooser;
+use scaffold_zome::scaffold_zome;
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+/// Scaffolds an integrity and a coordinator zome crate and adds them to a nixified DNA.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Name of the zome that's being scaffolded
+    zome_name: String,
+
+    /// Folder in which to create the integrity and coordinator crates [default: zomes/<ZOME_NAME>]
+    #[arg(long)]
+    zome_folder: Option<PathBuf>,
+
+    /// DNA of the local repository in which the zome should be scaffolded
+    #[arg(long)]
+    local_dna_to_add_the_zome_to: Option<String>,
+
+    /// Error with the list of valid choices instead of prompting when a choice needs to be made.
+    /// This is always the case when there is no terminal to prompt in.
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// File with the answers to the choices that need to be made, one per line, to use instead of prompting.
+    #[arg(long)]
+    answers_file: Option<PathBuf>,
+
+    /// The path of the file tree to modify.
+    #[clap(long, default_value = "./.")]
+    pub path: PathBuf,
+}
+
+fn main() -> ExitCode {
+    if let Err(err) = internal_main() {
+        eprintln!("{}", format!("Error: {err:?}").red());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn internal_main() -> Result<()> {
+    let args = Args::parse();
+
+    let file_tree = file_tree_utils::load_directory_into_memory(&args.path)?;
+    let chooser = build_chooser(args.non_interactive, args.answers_file)?;
+
+    let zome_folder = args
+        .zome_folder
+        .unwrap_or(PathBuf::from("zomes").join(&args.zome_name));
+
+    let file_tree = scaffold_zome(
+        file_tree,
+        args.zome_name.clone(),
+        zome_folder,
+        args.local_dna_to_add_the_zome_to,
+        chooser.as_ref(),
+    )?;
+
+    let file_tree = MergeableFileSystemTree::<OsString, String>::from(file_tree);
+
+    file_tree.build(&args.path)?;
+
+    println!(
+        "{}",
+        format!("Successfully scaffolded zome {}", args.zome_name.bold()).green()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,391 @@
+use file_tree_utils::{create_dir_all, dir_exists, insert_file, map_file, FileTree, FileTreeError};
+use holochain_scaffolding_utils::{add_zome_to_nixified_dna, get_or_choose_nixified_dna};
+use nix_scaffolding_utils::{add_flake_import, NixScaffoldingUtilsError};
+use prompt_utils::Chooser;
+use rust_scaffolding_utils::{add_member_to_workspace, add_workspace_dependency};
+use std::path::{Component, PathBuf};
+use thiserror::Error;
+
+/// Versions of hdi and hdk added to the workspace dependencies when the workspace doesn't declare them
+const HDI_VERSION: &str = "0.4.2";
+const HDK_VERSION: &str = "0.3.2";
+
+#[derive(Error, Debug)]
+pub enum ScaffoldZomeError {
+    #[error(transparent)]
+    NixScaffoldingUtilsError(#[from] NixScaffoldingUtilsError),
+
+    #[error(transparent)]
+    HolochainScaffoldingUtilsError(#[from] holochain_scaffolding_utils::Error),
+
+    #[error(transparent)]
+    RustScaffoldingUtilsError(#[from] rust_scaffolding_utils::Error),
+
+    #[error(transparent)]
+    FileTreeError(#[from] FileTreeError),
+
+    #[error("The folder {0:?} already exists.")]
+    FolderAlreadyExistsError(PathBuf),
+}
+
+/// Creates an integrity and a coordinator zome crate in the given folder, adds them to the cargo
+/// workspace and to the flake.nix imports, and adds them to the given DNA
+pub fn scaffold_zome(
+    mut file_tree: FileTree,
+    zome_name: String,
+    zome_folder: PathBuf,
+    local_dna_to_add_the_zome_to: Option<String>,
+    chooser: &dyn Chooser,
+) -> Result<FileTree, ScaffoldZomeError> {
+    let zome_folder: PathBuf = zome_folder
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+    let integrity_zome_name = format!("{zome_name}_integrity");
+    let integrity_folder = zome_folder.join("integrity");
+    let coordinator_folder = zome_folder.join("coordinator");
+
+    for folder in [&integrity_folder, &coordinator_folder] {
+        if dir_exists(&file_tree, folder) {
+            return Err(ScaffoldZomeError::FolderAlreadyExistsError(folder.clone()));
+        }
+    }
+
+    let dna = get_or_choose_nixified_dna(
+        &file_tree,
+        local_dna_to_add_the_zome_to,
+        &format!(
+            "Multiple DNAs were found in this repository, choose one to scaffold the {zome_name} zome into:"
+        ),
+        chooser,
+    )?;
+
+    create_dir_all(&mut file_tree, &integrity_folder.join("src"))?;
+    insert_file(
+        &mut file_tree,
+        &integrity_folder.join("Cargo.toml"),
+        &integrity_zome_cargo_toml(&integrity_zome_name),
+    )?;
+    insert_file(
+        &mut file_tree,
+        &integrity_folder.join("src").join("lib.rs"),
+        &integrity_zome_lib_rs(),
+    )?;
+    insert_file(
+        &mut file_tree,
+        &integrity_folder.join("zome.nix"),
+        &zome_nix(&integrity_zome_name),
+    )?;
+
+    create_dir_all(&mut file_tree, &coordinator_folder.join("src"))?;
+    insert_file(
+        &mut file_tree,
+        &coordinator_folder.join("Cargo.toml"),
+        &coordinator_zome_cargo_toml(&zome_name, &integrity_zome_name),
+    )?;
+    insert_file(
+        &mut file_tree,
+        &coordinator_folder.join("src").join("lib.rs"),
+        &coordinator_zome_lib_rs(),
+    )?;
+    insert_file(
+        &mut file_tree,
+        &coordinator_folder.join("zome.nix"),
+        &zome_nix(&zome_name),
+    )?;
+
+    eprintln!("Added the integrity zome {integrity_zome_name} to {integrity_folder:?} and the coordinator zome {zome_name} to {coordinator_folder:?}.");
+
+    let cargo_toml_path = PathBuf::from("Cargo.toml");
+    for folder in [&integrity_folder, &coordinator_folder] {
+        map_file(&mut file_tree, &cargo_toml_path, |cargo_toml_content| {
+            add_member_to_workspace(
+                &(cargo_toml_path.clone(), cargo_toml_content),
+                folder.to_str().unwrap().to_string(),
+            )
+        })?;
+    }
+
+    for (dependency, version) in [("hdi", HDI_VERSION), ("hdk", HDK_VERSION)] {
+        map_file(&mut file_tree, &cargo_toml_path, |cargo_toml_content| {
+            add_workspace_dependency(
+                &(cargo_toml_path.clone(), cargo_toml_content),
+                dependency.to_string(),
+                version.to_string(),
+            )
+        })?;
+    }
+
+    eprintln!("Added the new zome crates to the workspace members in Cargo.toml.");
+
+    for folder in [&integrity_folder, &coordinator_folder] {
+        file_tree = add_flake_import(
+            file_tree,
+            format!("./{}", folder.join("zome.nix").to_str().unwrap()),
+        )?;
+    }
+
+    add_zome_to_nixified_dna(
+        &mut file_tree,
+        &dna,
+        Some(integrity_zome_name.clone()),
+        Some(zome_name),
+        Some(integrity_zome_name),
+        &String::from("self'.packages"),
+    )?;
+
+    Ok(file_tree)
+}
+
+fn integrity_zome_cargo_toml(integrity_zome_name: &String) -> String {
+    format!(
+        r#"[package]
+name = "{integrity_zome_name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+name = "{integrity_zome_name}"
+
+[dependencies]
+hdi = {{ workspace = true }}
+serde = "1"
+"#
+    )
+}
+
+fn coordinator_zome_cargo_toml(zome_name: &String, integrity_zome_name: &String) -> String {
+    format!(
+        r#"[package]
+name = "{zome_name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+name = "{zome_name}"
+
+[dependencies]
+hdk = {{ workspace = true }}
+serde = "1"
+{integrity_zome_name} = {{ path = "../integrity" }}
+"#
+    )
+}
+
+fn integrity_zome_lib_rs() -> String {
+    String::from(
+        r#"use hdi::prelude::*;
+
+#[hdk_extern]
+pub fn validate(_op: Op) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Valid)
+}
+"#,
+    )
+}
+
+fn coordinator_zome_lib_rs() -> String {
+    String::from(
+        r#"use hdk::prelude::*;
+
+#[hdk_extern]
+pub fn init(_: ()) -> ExternResult<InitCallbackResult> {
+    Ok(InitCallbackResult::Pass)
+}
+"#,
+    )
+}
+
+fn zome_nix(crate_name: &String) -> String {
+    format!(
+        r#"{{ inputs, ... }}:
+
+{{
+  perSystem = {{ inputs', self', system, ... }}: {{
+    packages.{crate_name} = inputs.hc-infra.outputs.builders.${{system}}.rustZome {{
+      workspacePath = inputs.self.outPath;
+      crateCargoToml = ./Cargo.toml;
+      cargoArtifacts = inputs'.hc-infra.packages.zomeCargoArtifacts;
+    }};
+  }};
+}}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use build_fs_tree::{dir, file};
+    use file_tree_utils::file_content;
+    use holochain_scaffolding_utils::test_utils::{empty_dna_nix, empty_dna_yaml};
+    use pretty_assertions::assert_eq;
+    use prompt_utils::DialoguerChooser;
+
+    #[test]
+    fn scaffold_zome_test() {
+        let repo: FileTree = dir! {
+            "Cargo.toml" => file!(workspace_cargo_toml()),
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+        };
+
+        let repo = scaffold_zome(
+            repo,
+            "posts".into(),
+            PathBuf::from("zomes/posts"),
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("Cargo.toml").as_path()).unwrap(),
+            r#"[workspace]
+members = ["crates/*", "zomes/posts/integrity", "zomes/posts/coordinator"]
+resolver = "2"
+
+[workspace.dependencies]
+hdi = "0.4.2"
+hdk = "0.3.2"
+"#
+        );
+
+        assert_eq!(
+            file_content(
+                &repo,
+                PathBuf::from("zomes/posts/coordinator/zome.nix").as_path()
+            )
+            .unwrap(),
+            r#"{ inputs, ... }:
+
+{
+  perSystem = { inputs', self', system, ... }: {
+    packages.posts = inputs.hc-infra.outputs.builders.${system}.rustZome {
+      workspacePath = inputs.self.outPath;
+      crateCargoToml = ./Cargo.toml;
+      cargoArtifacts = inputs'.hc-infra.packages.zomeCargoArtifacts;
+    };
+  };
+}
+"#
+        );
+
+        assert!(file_content(
+            &repo,
+            PathBuf::from("zomes/posts/coordinator/Cargo.toml").as_path()
+        )
+        .unwrap()
+        .contains(r#"posts_integrity = { path = "../integrity" }"#));
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("flake.nix").as_path()).unwrap(),
+            r#"{
+  outputs = inputs @ { ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake
+    {
+      inherit inputs;
+    }
+    {
+      imports = [
+        ./zomes/posts/coordinator/zome.nix
+        ./zomes/posts/integrity/zome.nix
+        ./dna.nix
+      ];
+    };
+}
+"#
+        );
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("dna.nix").as_path()).unwrap(),
+            r#"{ inputs, ... }:
+
+{
+  perSystem =
+    { inputs'
+    , self'
+    , system
+    , ...
+    }: {
+      packages.my_dna = inputs.hc-infra.outputs.builders.${system}.dna {
+        dnaManifest = ./dna.yaml;
+        zomes = {
+          posts_integrity = self'.packages.posts_integrity;
+          posts = self'.packages.posts;
+        };
+      };
+    };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn scaffold_zome_keeps_existing_workspace_dependencies_test() {
+        let repo: FileTree = dir! {
+            "Cargo.toml" => file!(String::from(r#"[workspace]
+members = ["crates/*"]
+resolver = "2"
+
+[workspace.dependencies]
+hdi = "0.5.0"
+hdk = "0.4.0"
+"#)),
+            "flake.nix" => file!(default_flake_nix()),
+            "dna.nix" => file!(empty_dna_nix()),
+            "dna.yaml" => file!(empty_dna_yaml("mydna")),
+        };
+
+        let repo = scaffold_zome(
+            repo,
+            "posts".into(),
+            PathBuf::from("zomes/posts"),
+            None,
+            &DialoguerChooser::new(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            file_content(&repo, PathBuf::from("Cargo.toml").as_path()).unwrap(),
+            r#"[workspace]
+members = ["crates/*", "zomes/posts/integrity", "zomes/posts/coordinator"]
+resolver = "2"
+
+[workspace.dependencies]
+hdi = "0.5.0"
+hdk = "0.4.0"
+"#
+        );
+    }
+
+    fn workspace_cargo_toml() -> String {
+        String::from(
+            r#"[workspace]
+members = ["crates/*"]
+resolver = "2"
+"#,
+        )
+    }
+
+    fn default_flake_nix() -> String {
+        String::from(
+            r#"{
+  outputs = inputs @ { ... }:
+    inputs.holonix.inputs.flake-parts.lib.mkFlake
+    {
+      inherit inputs;
+    }
+    {
+      imports = [
+        ./dna.nix
+      ];
+    };
+}
+"#,
+        )
+    }
+}
@@ -1,13 +1,15 @@
 use anyhow::Result;
 use colored::Colorize;
 use ignore::Walk;
+use npm_scaffolding_utils::{
+    guess_package_manager_in_directory, to_string_preserving_format, NpmDependencySpec,
+    NpmScaffoldingUtilsError, PackageManager, NPM_DEPENDENCY_SECTIONS,
+};
 use parse_flake_lock::{FlakeLock, FlakeLockParseError, Node};
-use regex::Regex;
 use serde_json::Value;
 use std::{
     env::current_dir,
-    fs::File,
-    io::BufReader,
+    path::Path,
     process::{Command, Stdio},
 };
 use thiserror::Error;
@@ -24,19 +26,61 @@ pub enum SynchronizeNpmGitDependenciesWithNixError {
     SerdeJsonError(#[from] serde_json::Error),
 
     #[error(transparent)]
-    RegexError(#[from] regex::Error),
+    NpmScaffoldingUtilsError(#[from] NpmScaffoldingUtilsError),
 
-    #[error("Error parsing git {0} for dependency {1}")]
-    ParseGitRepositoryError(String, String),
+    #[error("The install command \"{0}\" failed: {1}")]
+    InstallCommandError(String, String),
 }
 
+/// Points the NPM git dependencies of the current directory to the revisions locked in its flake.lock,
+/// and installs them with the package manager of the workspace if any of them changed
 pub fn synchronize_npm_git_dependencies_with_nix(
 ) -> Result<(), SynchronizeNpmGitDependenciesWithNixError> {
-    let flake_lock = current_dir()?.join("flake.lock");
+    let current_dir = current_dir()?;
+    let replaced_some_dep = synchronize_npm_git_dependencies_sources(current_dir.as_path())?;
+
+    if replaced_some_dep {
+        // pnpm was the only package manager supported before, so keep it when there is nothing to guess from
+        let package_manager =
+            guess_package_manager_in_directory(&current_dir).unwrap_or(PackageManager::Pnpm);
+        let install_command = package_manager.install_command();
+        let mut install_words = install_command.split_whitespace();
+
+        eprintln!("Running {install_command}...");
+        eprintln!("");
+        let status = Command::new(install_words.next().unwrap_or_default())
+            .args(install_words)
+            .stdout(Stdio::inherit())
+            .status()?;
+        if !status.success() {
+            return Err(
+                SynchronizeNpmGitDependenciesWithNixError::InstallCommandError(
+                    install_command,
+                    status.to_string(),
+                ),
+            );
+        }
+        eprintln!(
+            "{}",
+            "Successfully synchronized npm dependencies with nix".green()
+        );
+    }
+
+    Ok(())
+}
+
+/// Points the NPM git dependencies of the given directory to the revisions locked in its flake.lock,
+/// without installing them
+///
+/// Returns whether any of the dependencies changed
+pub fn synchronize_npm_git_dependencies_sources(
+    path: &Path,
+) -> Result<bool, SynchronizeNpmGitDependenciesWithNixError> {
+    let flake_lock = path.join("flake.lock");
 
     // Return silently if no "flake.lock" file exists
     if !flake_lock.exists() {
-        return Ok(());
+        return Ok(false);
     }
 
     let flake_lock = FlakeLock::new(flake_lock.as_path())?;
@@ -44,97 +88,74 @@ pub fn synchronize_npm_git_dependencies_with_nix(
     let mut announced = false;
     let mut replaced_some_dep = false;
 
-    for entry in Walk::new(".").into_iter().filter_map(|e| e.ok()) {
+    for entry in Walk::new(path).into_iter().filter_map(|e| e.ok()) {
         let f_name = entry.file_name().to_string_lossy();
 
         if f_name == "package.json" {
-            let file = File::open(entry.path())?;
-            let reader = BufReader::new(file);
-            let mut package_json_contents: Value = serde_json::from_reader(reader)?;
+            let original_package_json = std::fs::read_to_string(entry.path())?;
+            let mut package_json_contents: Value = serde_json::from_str(&original_package_json)?;
 
-            if let Some(Value::Object(deps)) = package_json_contents.get_mut("dependencies") {
-                let re = Regex::new(r#"(github|gitlab):([^/]+)/([^#]+)#([^&"]+)(&.*)?$"#)?;
+            for section in NPM_DEPENDENCY_SECTIONS {
+                let Some(Value::Object(deps)) = package_json_contents.get_mut(section) else {
+                    continue;
+                };
 
                 for (_package, dependency_source) in deps {
-                    if let Value::String(dependency_source_str) = dependency_source.clone() {
-                        if let Some(captures) = re.captures(&dependency_source_str) {
-                            let git_host = captures
-                                .get(1)
-                                .ok_or(
-                                    SynchronizeNpmGitDependenciesWithNixError::ParseGitRepositoryError("username".into(), dependency_source_str.clone())
-)?
-                                .as_str();
-                            let git_owner = captures
-                                .get(2)
-                                .ok_or(
-                                    SynchronizeNpmGitDependenciesWithNixError::ParseGitRepositoryError("owner".into(), dependency_source_str.clone())
-)?
-                                .as_str();
-                            let git_repo = captures
-                                .get(3)
-                                .ok_or(
-                                    SynchronizeNpmGitDependenciesWithNixError::ParseGitRepositoryError("repository".into(), dependency_source_str.clone())
-)?
-                                .as_str();
-                            let revision = captures
-                                .get(4)
-                                .ok_or(
-                                    SynchronizeNpmGitDependenciesWithNixError::ParseGitRepositoryError("revision".into(), dependency_source_str.clone())
-)?
-                                .as_str();
-                            let query_arguments =
-                                captures.get(5).map(|c| c.as_str()).unwrap_or_default();
-
-                            for root_node in flake_lock.root.values() {
-                                if let Node::Repo(repo_node) = root_node {
-                                    if repo_node.locked.owner == git_owner
-                                        && repo_node.locked.repo == git_repo
-                                        && revision != repo_node.locked.rev
-                                    {
-                                        *dependency_source = Value::String(format!(
-                                            "{git_host}:{git_owner}/{git_repo}#{}{query_arguments}",
-                                            repo_node.locked.rev
-                                        ));
-
-                                        if !announced {
-                                            announced = true;
-                                            println!("");
-                                            println!(
+                    let Value::String(dependency_source_str) = dependency_source.clone() else {
+                        continue;
+                    };
+                    // Sources that are not valid are left for the package manager to complain about
+                    let Ok(mut spec) = NpmDependencySpec::parse(&dependency_source_str) else {
+                        continue;
+                    };
+                    let NpmDependencySpec::HostedGit {
+                        host,
+                        owner,
+                        repo,
+                        committish: Some(revision),
+                        ..
+                    } = spec.clone()
+                    else {
+                        continue;
+                    };
+                    let dependency_repo = format!("{}:{owner}/{repo}", host.prefix());
+
+                    for root_node in flake_lock.root.values() {
+                        if let Node::Repo(repo_node) = root_node {
+                            if repo_node.locked.owner == owner
+                                && repo_node.locked.repo == repo
+                                && revision != repo_node.locked.rev
+                            {
+                                spec.set_committish(repo_node.locked.rev.clone());
+                                *dependency_source = Value::String(spec.to_string());
+
+                                if !announced {
+                                    announced = true;
+                                    eprintln!("");
+                                    eprintln!(
                                         "Synchronizing npm git dependencies with the upstream nix sources..."
                                     );
-                                        }
+                                }
 
-                                        println!(
-                                    "  - Setting dependency \"{git_host}:{git_owner}/{git_repo}\" in file {:?} to rev \"{}\"",
-                                    entry.path(), repo_node.locked.rev
+                                eprintln!(
+                                    "  - Setting dependency \"{dependency_repo}\" in file {:?} to rev \"{}\"",
+                                    entry.path(),
+                                    repo_node.locked.rev
                                 );
-                                        replaced_some_dep = true;
-                                    }
-                                }
+                                replaced_some_dep = true;
                             }
                         }
                     }
                 }
             }
 
-            let st = serde_json::to_string_pretty(&package_json_contents)?;
+            let st = to_string_preserving_format(&original_package_json, &package_json_contents)?;
 
-            std::fs::write(entry.path(), st)?;
+            if st.ne(&original_package_json) {
+                std::fs::write(entry.path(), st)?;
+            }
         }
     }
 
-    if replaced_some_dep {
-        println!("Running pnpm install...");
-        println!("");
-        Command::new("pnpm")
-            .arg("install")
-            .stdout(Stdio::inherit())
-            .output()?;
-        println!(
-            "{}",
-            "Successfully synchronized npm dependencies with nix".green()
-        );
-    }
-
-    Ok(())
+    Ok(replaced_some_dep)
 }
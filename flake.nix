@@ -24,6 +24,8 @@
 
       imports = [
         ./crates/scaffold_remote_zome/default.nix
+        ./crates/scaffold_happ/default.nix
+        ./crates/scaffold_zome/default.nix
         ./crates/compare_dnas_integrity/default.nix
         ./crates/zome_wasm_hash/default.nix
         ./crates/sync_npm_git_dependencies_with_nix/default.nix